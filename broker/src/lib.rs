@@ -0,0 +1,65 @@
+//! Broker abstraction of the pipeline.
+//!
+//! The merger joins the records it reads from a `Source` and writes its outputs to a `Sink`. In
+//! production the merger implements them itself over Kafka: its sink is the transactional
+//! producer, and its sources are the records consumed for a transaction and the state changelog
+//! replayed on startup. `InMemoryBroker` implements both in tests, and is also the sink of the
+//! producer there, so that the whole pipeline can run in one process. The saver consumes Kafka
+//! directly.
+
+use std::error::Error;
+use std::fmt::{self, Display};
+use std::time::Duration;
+
+pub mod memory;
+
+pub use memory::{InMemoryBroker, InMemoryConsumer};
+
+/// A record read from a topic
+#[derive(Debug, Clone, PartialEq)]
+pub struct Record {
+    pub topic: String,
+    pub partition: i32,
+    pub offset: i64,
+    pub key: Option<String>,
+    pub payload: Option<Vec<u8>>,
+    pub headers: Vec<(String, String)>,
+}
+
+impl Record {
+    /// Value of the first header named `name`
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+#[derive(Debug)]
+pub struct BrokerError(pub String);
+
+impl Display for BrokerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl Error for BrokerError {}
+
+pub trait Source {
+    /// Next record of the subscribed topics, `None` when nothing arrives within `timeout`
+    fn poll(&mut self, timeout: Duration) -> Option<Result<Record, BrokerError>>;
+}
+
+pub trait Sink {
+    /// Sends a record carrying `headers`, as name and value pairs
+    fn send_with_headers(&self, topic: &str, key: &str, payload: &[u8], headers: &[(&str, &str)]) -> Result<(), BrokerError>;
+
+    fn send(&self, topic: &str, key: &str, payload: &[u8]) -> Result<(), BrokerError> {
+        self.send_with_headers(topic, key, payload, &[])
+    }
+
+    /// Sends a record without payload, deleting the key from a compacted topic
+    fn send_tombstone(&self, topic: &str, key: &str) -> Result<(), BrokerError>;
+}
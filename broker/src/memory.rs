@@ -0,0 +1,78 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::{BrokerError, Record, Sink, Source};
+
+/// Topics kept in memory, each with a single partition
+#[derive(Debug, Default)]
+pub struct InMemoryBroker {
+    topics: Mutex<HashMap<String, Vec<Record>>>,
+}
+
+impl InMemoryBroker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records of a topic, in the order they were sent
+    pub fn records(&self, topic: &str) -> Vec<Record> {
+        self.topics.lock().unwrap().get(topic).cloned().unwrap_or_default()
+    }
+
+    /// Consumer reading the topics from their beginning
+    pub fn subscribe(self: &Arc<Self>, topics: &[&str]) -> InMemoryConsumer {
+        InMemoryConsumer {
+            broker: Arc::clone(self),
+            positions: topics.iter().map(|topic| (topic.to_string(), 0)).collect(),
+        }
+    }
+
+    fn append(&self, topic: &str, key: &str, payload: Option<Vec<u8>>, headers: &[(&str, &str)]) {
+        let mut topics = self.topics.lock().unwrap();
+        let records = topics.entry(topic.to_string()).or_default();
+        records.push(Record {
+            topic: topic.to_string(),
+            partition: 0,
+            offset: records.len() as i64,
+            key: Some(key.to_string()),
+            payload,
+            headers: headers
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+        });
+    }
+}
+
+impl Sink for InMemoryBroker {
+    fn send_with_headers(&self, topic: &str, key: &str, payload: &[u8], headers: &[(&str, &str)]) -> Result<(), BrokerError> {
+        self.append(topic, key, Some(payload.to_vec()), headers);
+        Ok(())
+    }
+
+    fn send_tombstone(&self, topic: &str, key: &str) -> Result<(), BrokerError> {
+        self.append(topic, key, None, &[]);
+        Ok(())
+    }
+}
+
+/// Consumer of an `InMemoryBroker`, reading its topics in the order of the subscription
+pub struct InMemoryConsumer {
+    broker: Arc<InMemoryBroker>,
+    positions: Vec<(String, usize)>,
+}
+
+impl Source for InMemoryConsumer {
+    /// Never waits: returns `None` as soon as every subscribed topic is read
+    fn poll(&mut self, _timeout: Duration) -> Option<Result<Record, BrokerError>> {
+        let topics = self.broker.topics.lock().unwrap();
+        for (topic, position) in &mut self.positions {
+            if let Some(record) = topics.get(topic.as_str()).and_then(|records| records.get(*position)) {
+                *position += 1;
+                return Some(Ok(record.clone()));
+            }
+        }
+        None
+    }
+}
@@ -0,0 +1,2 @@
+//! End-to-end tests of the pipeline, running the producer, the merger and the saver in one
+//! process on top of an in-memory broker. See `tests/`.
@@ -0,0 +1,185 @@
+use std::fs::File;
+use std::sync::Arc;
+
+use kafkamion_model::erasure::ErasedObject;
+use kafkamion_model::report::{Break, Report};
+use parquet::column::writer::ColumnWriter;
+use parquet::data_type::ByteArray;
+use parquet::file::properties::WriterProperties;
+use parquet::file::writer::{FileWriter, RowGroupWriter, SerializedFileWriter};
+use parquet::schema::parser::parse_message_type;
+use saver::erasure::erase_driver;
+use saver::reader::load_from_parquet;
+use saver::store::{LocalStore, ObjectStore};
+use saver::writer::save_to_parquet;
+
+/// Schema of the first saver, before the metrics, the offsets and the breaks
+const LEGACY_SCHEMA: &str = "
+    message schema {
+        REQUIRED BINARY driver_id (UTF8);
+        REQUIRED BINARY first_name (UTF8);
+        REQUIRED BINARY last_name (UTF8);
+        REQUIRED BINARY email (UTF8);
+        REQUIRED BINARY phone (UTF8);
+        REQUIRED BINARY truck_id (UTF8);
+        REQUIRED BINARY immatriculation;
+        REQUIRED BINARY start_time (UTF8);
+        REQUIRED BINARY end_time (UTF8);
+        REQUIRED BINARY rest_time (UTF8);
+        REQUIRED DOUBLE latitude_start;
+        REQUIRED DOUBLE longitude_start;
+        REQUIRED BINARY timestamp_start (UTF8);
+        REQUIRED DOUBLE latitude_end;
+        REQUIRED DOUBLE longitude_end;
+        REQUIRED BINARY timestamp_end (UTF8);
+        REQUIRED DOUBLE latitude_rest;
+        REQUIRED DOUBLE longitude_rest;
+        REQUIRED BINARY timestamp_rest (UTF8);
+    }
+";
+
+/// A report of the driver holding only the values of the first schema, the others being the
+/// defaults they are read as from a legacy file
+fn report(driver_id: &str) -> Report {
+    Report {
+        driver_id: driver_id.to_string(),
+        first_name: "Jane".to_string(),
+        last_name: "Doe".to_string(),
+        email: "jane.doe@example.com".to_string(),
+        phone: "+33600000000".to_string(),
+        truck_id: "T-1".to_string(),
+        immatriculation: "AB-123-CD".to_string(),
+        start_time: "1736143200000000".to_string(),
+        end_time: "1736172000000000".to_string(),
+        rest_time: "2700".to_string(),
+        latitude_start: 48.8566,
+        longitude_start: 2.3522,
+        timestamp_start: "1736143200000000".to_string(),
+        latitude_end: 48.9,
+        longitude_end: 2.4,
+        timestamp_end: "1736172000000000".to_string(),
+        latitude_rest: 48.87,
+        longitude_rest: 2.37,
+        timestamp_rest: "1736157600000000".to_string(),
+        shift_duration: 0,
+        driving_duration: 0,
+        break_duration: 0,
+        distance_km: 0.0,
+        average_speed_kmh: 0.0,
+        max_speed_kmh: 0.0,
+        offset_start: 0,
+        interpolated_start: false,
+        offset_end: 0,
+        interpolated_end: false,
+        offset_rest: 0,
+        interpolated_rest: false,
+        breaks: Vec::new(),
+    }
+}
+
+/// Values of a column of the legacy schema
+enum Column {
+    Text(Vec<ByteArray>),
+    Number(Vec<f64>),
+}
+
+/// Writes reports in the 19 columns of the legacy schema
+fn save_legacy(reports: &[Report], parquet_file: &str) {
+    let text = |field: fn(&Report) -> &str| Column::Text(reports.iter().map(|report| ByteArray::from(field(report))).collect());
+    let number = |field: fn(&Report) -> f64| Column::Number(reports.iter().map(field).collect());
+    let columns = [
+        text(|report| &report.driver_id),
+        text(|report| &report.first_name),
+        text(|report| &report.last_name),
+        text(|report| &report.email),
+        text(|report| &report.phone),
+        text(|report| &report.truck_id),
+        text(|report| &report.immatriculation),
+        text(|report| &report.start_time),
+        text(|report| &report.end_time),
+        text(|report| &report.rest_time),
+        number(|report| report.latitude_start),
+        number(|report| report.longitude_start),
+        text(|report| &report.timestamp_start),
+        number(|report| report.latitude_end),
+        number(|report| report.longitude_end),
+        text(|report| &report.timestamp_end),
+        number(|report| report.latitude_rest),
+        number(|report| report.longitude_rest),
+        text(|report| &report.timestamp_rest),
+    ];
+
+    let schema = Arc::new(parse_message_type(LEGACY_SCHEMA).unwrap());
+    let props = Arc::new(WriterProperties::builder().build());
+    let mut writer = SerializedFileWriter::new(File::create(parquet_file).unwrap(), schema, props).unwrap();
+    let mut row_group_writer = writer.next_row_group().unwrap();
+    for column in columns {
+        let mut col_writer = row_group_writer.next_column().unwrap().unwrap();
+        match (&mut col_writer, column) {
+            (ColumnWriter::ByteArrayColumnWriter(typed_writer), Column::Text(values)) => {
+                typed_writer.write_batch(&values, None, None).unwrap();
+            }
+            (ColumnWriter::DoubleColumnWriter(typed_writer), Column::Number(values)) => {
+                typed_writer.write_batch(&values, None, None).unwrap();
+            }
+            _ => panic!("Column of another type than in the legacy schema"),
+        }
+        row_group_writer.close_column(col_writer).unwrap();
+    }
+    writer.close_row_group(row_group_writer).unwrap();
+    writer.close().unwrap();
+}
+
+#[tokio::test]
+async fn legacy_objects_are_erased_and_unreadable_ones_recorded() {
+    let spool = tempfile::tempdir().unwrap();
+    let objects = tempfile::tempdir().unwrap();
+    let store = LocalStore::new(objects.path());
+
+    // the first saver wrote 19 columns, the current one the breaks too
+    let legacy = [report("D-1"), report("D-2")];
+    let legacy_file = spool.path().join("messages.parquet");
+    save_legacy(&legacy, legacy_file.to_str().unwrap());
+    store.upload(legacy_file.to_str().unwrap(), "kafkamion/messages.parquet").await.unwrap();
+    let current = Report {
+        breaks: vec![Break {
+            start_time: "1736157600000000".to_string(),
+            end_time: "1736160300000000".to_string(),
+            duration: 2700,
+            latitude: 48.87,
+            longitude: 2.37,
+            offset: -30,
+            interpolated: true,
+        }],
+        shift_duration: 28800,
+        ..report("D-1")
+    };
+    let current_file = spool.path().join("reports.parquet");
+    save_to_parquet(std::slice::from_ref(&current), current_file.to_str().unwrap()).unwrap();
+    store.upload(current_file.to_str().unwrap(), "kafkamion/reports/reports.parquet").await.unwrap();
+    let broken_file = spool.path().join("broken.parquet");
+    std::fs::write(&broken_file, b"not a parquet file").unwrap();
+    store.upload(broken_file.to_str().unwrap(), "kafkamion/uploads/broken.parquet").await.unwrap();
+
+    let path = store.path("kafkamion/messages.parquet");
+    assert_eq!(load_from_parquet(path.to_str().unwrap()).unwrap(), legacy);
+
+    let audit = erase_driver(&store, "kafkamion", "D-1", spool.path()).await.unwrap();
+    let erased: Vec<ErasedObject> = ["kafkamion/messages.parquet", "kafkamion/reports/reports.parquet"]
+        .into_iter()
+        .map(|key| ErasedObject { key: key.to_string(), rows: 1 })
+        .collect();
+    assert_eq!(audit.objects, erased);
+    let failed: Vec<&str> = audit.failures.iter().map(|failure| failure.key.as_str()).collect();
+    assert_eq!(failed, ["kafkamion/uploads/broken.parquet"]);
+
+    // the legacy object is rewritten with the current schema
+    let rewritten = load_from_parquet(path.to_str().unwrap()).unwrap();
+    assert_eq!((rewritten[0].first_name.as_str(), rewritten[0].email.as_str()), ("", ""));
+    assert_eq!(rewritten[1], legacy[1]);
+    let path = store.path("kafkamion/reports/reports.parquet");
+    let rewritten = load_from_parquet(path.to_str().unwrap()).unwrap();
+    assert_eq!((rewritten[0].last_name.as_str(), rewritten[0].phone.as_str()), ("", ""));
+    assert_eq!((&rewritten[0].breaks, rewritten[0].shift_duration), (&current.breaks, 28800));
+    assert_eq!(store.list("kafkamion/erasures/").await.unwrap().len(), 1);
+}
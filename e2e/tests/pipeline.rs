@@ -0,0 +1,206 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use kafkamion_broker::InMemoryBroker;
+use kafkamion_model::alert::{Alert, AlertType};
+use kafkamion_model::compliance::Violation;
+use kafkamion_model::erasure::ErasedObject;
+use kafkamion_model::geofence::{GeofenceEvent, GeofenceEventType};
+use kafkamion_model::report::Report;
+use merger::config::Topics;
+use merger::consumer::pump;
+use merger::geofence::GeofenceRegistry;
+use merger::join::Joiner;
+use producer::events::driver::DriverEvent;
+use producer::events::position::PositionEvent;
+use producer::events::rng::SeededRng;
+use producer::events::time_registration::{ShiftConfig, TimeRegistrationEvent};
+use producer::events::truck::TruckEvent;
+use producer::events::{generate_and_publish, generate_and_publish_as_group};
+use producer::sink::EventSink;
+use saver::erasure::erase_driver;
+use saver::reader::load_from_parquet;
+use saver::store::{LocalStore, ObjectStore};
+use saver::writer::save_to_parquet;
+
+const SEED: u64 = 42;
+const COUNT: i32 = 10;
+const BREAKS: usize = 2;
+const BREAK_MINUTES: i64 = 30;
+
+fn topics() -> Topics {
+    Topics {
+        entity: "entity_topic".to_string(),
+        time_registration: "time_registration_topic".to_string(),
+        position: "position_topic".to_string(),
+        report: "report_topic".to_string(),
+        changelog: "merger_state_changelog".to_string(),
+        late: "late_events_topic".to_string(),
+        compliance: "compliance_topic".to_string(),
+        anomalies: "anomalies_topic".to_string(),
+        geofence: "geofence_topic".to_string(),
+        alerts: "alerts_topic".to_string(),
+        erasure: "erasure_topic".to_string(),
+        dead_letter: "merger_dead_letter".to_string(),
+        retries: Vec::new(),
+    }
+}
+
+fn shift_config() -> ShiftConfig {
+    ShiftConfig {
+        shift_hours: 9.0,
+        breaks: BREAKS as u32,
+        break_minutes: BREAK_MINUTES,
+        night_shift_ratio: 0.0,
+        weeks: 0,
+        rest_days: 2,
+    }
+}
+
+#[tokio::test]
+async fn generated_shifts_are_joined_and_stored_as_parquet() {
+    let broker = Arc::new(InMemoryBroker::new());
+    let topics = topics();
+
+    // producer: the same generators as the `position` command
+    let sink: Arc<dyn EventSink> = broker.clone();
+    let rng = SeededRng::new(Some(SEED));
+    let drivers = generate_and_publish(Arc::new(DriverEvent::new(rng.fork())), sink.clone(), COUNT, &topics.entity, "driver").await;
+    let trucks = generate_and_publish(Arc::new(TruckEvent::new(rng.fork())), sink.clone(), COUNT, &topics.entity, "truck").await;
+    let shifts = generate_and_publish_as_group(
+        Arc::new(TimeRegistrationEvent::new(drivers.clone(), trucks.clone(), shift_config(), rng.fork())),
+        sink.clone(),
+        COUNT,
+        &topics.time_registration,
+        "time_registration",
+    )
+    .await;
+    let positions = generate_and_publish(
+        Arc::new(PositionEvent::new(shifts.clone(), chrono::Duration::seconds(60), rng.fork())),
+        sink.clone(),
+        COUNT,
+        &topics.position,
+        "position",
+    )
+    .await;
+
+    // merger
+    let geofences = GeofenceRegistry::load("../merger/src/resource/geofences.geojson").unwrap();
+    let joiner = Mutex::new(Joiner::new().with_geofences(geofences));
+    let mut source = broker.subscribe(&[&topics.entity, &topics.time_registration, &topics.position]);
+    let consumed = pump(&mut source, broker.as_ref(), &joiner, &topics, Duration::ZERO).unwrap();
+    assert_eq!(
+        consumed,
+        drivers.len() + trucks.len() + shifts.iter().map(Vec::len).sum::<usize>() + positions.len()
+    );
+
+    let reports: Vec<Report> = broker
+        .records(&topics.report)
+        .iter()
+        .map(|record| serde_json::from_slice(record.payload.as_deref().unwrap()).unwrap())
+        .collect();
+
+    // a report is expected for every shift whose truck was followed by the position generator
+    let expected: HashSet<(String, String)> = shifts
+        .iter()
+        .map(|shift| &shift[0])
+        .filter(|start| positions.iter().any(|p| p.truck_id == start.truck_id && p.timestamp == start.timestamp))
+        .map(|start| (start.driver_id.clone(), start.timestamp.clone()))
+        .collect();
+    let joined: HashSet<(String, String)> = reports
+        .iter()
+        .map(|report| (report.driver_id.clone(), report.start_time.clone()))
+        .collect();
+    assert!(!reports.is_empty());
+    assert_eq!(joined, expected);
+    assert!(broker.records(&topics.dead_letter).is_empty());
+    assert!(broker.records(&topics.anomalies).is_empty());
+    // the schedules of random drivers on random trucks may overlap, but the trucks only move on shift
+    for record in broker.records(&topics.alerts) {
+        let alert: Alert = serde_json::from_slice(record.payload.as_deref().unwrap()).unwrap();
+        assert_ne!(alert.kind, AlertType::TruckMovingWithoutDriver);
+        let key = if alert.kind == AlertType::DriverOnTwoTrucks { &alert.driver_ids[0] } else { &alert.truck_ids[0] };
+        assert_eq!(record.key.as_deref(), Some(key.as_str()));
+    }
+    for record in broker.records(&topics.compliance) {
+        let violation: Violation = serde_json::from_slice(record.payload.as_deref().unwrap()).unwrap();
+        assert_eq!(record.key.as_deref(), Some(violation.driver_id.as_str()));
+    }
+    // the trucks start from a depot
+    let events: Vec<GeofenceEvent> = broker
+        .records(&topics.geofence)
+        .iter()
+        .map(|record| serde_json::from_slice(record.payload.as_deref().unwrap()).unwrap())
+        .collect();
+    assert!(events.iter().any(|event| event.kind == GeofenceEventType::Enter && event.geofence_id.starts_with("depot-")));
+    for event in &events {
+        assert_eq!(event.dwell_duration.is_some(), event.kind == GeofenceEventType::Exit);
+    }
+
+    let drivers: HashMap<_, _> = drivers.iter().map(|driver| (&driver.driver_id, driver)).collect();
+    let trucks: HashMap<_, _> = trucks.iter().map(|truck| (&truck.truck_id, truck)).collect();
+    for report in &reports {
+        let driver = drivers[&report.driver_id];
+        assert_eq!((&report.first_name, &report.email), (&driver.first_name, &driver.email));
+        assert_eq!(report.immatriculation, trucks[&report.truck_id].immatriculation);
+        assert_eq!(report.timestamp_start, report.start_time);
+        assert_eq!(report.timestamp_end, report.end_time);
+        assert_eq!(report.breaks.len(), BREAKS);
+        for rest in &report.breaks {
+            assert!((BREAK_MINUTES * 60..(BREAK_MINUTES + 15) * 60).contains(&rest.duration));
+        }
+        let rest_time: i64 = report.rest_time.parse().unwrap();
+        assert_eq!(rest_time, report.breaks.iter().map(|rest| rest.duration).sum::<i64>());
+        assert_eq!(report.timestamp_rest, report.breaks[0].start_time);
+
+        let shift_duration = (report.end_time.parse::<i64>().unwrap() - report.start_time.parse::<i64>().unwrap()) / 1_000_000;
+        assert_eq!(report.shift_duration, shift_duration);
+        assert_eq!(report.break_duration, rest_time);
+        assert_eq!(report.driving_duration, shift_duration - rest_time);
+        assert!(report.distance_km > 0.0);
+        assert!(report.average_speed_kmh > 0.0 && report.average_speed_kmh <= report.max_speed_kmh);
+        assert!(report.max_speed_kmh < 200.0);
+        // the producer gives the positions the timestamps of the registrations
+        assert_eq!((report.offset_start, report.offset_end, report.offset_rest), (0, 0, 0));
+        assert!(!report.interpolated_start && !report.interpolated_end && !report.interpolated_rest);
+    }
+
+    // saver
+    let spool = tempfile::tempdir().unwrap();
+    let objects = tempfile::tempdir().unwrap();
+    let store = LocalStore::new(objects.path());
+    let parquet_file = spool.path().join("reports.parquet");
+    let parquet_file = parquet_file.to_str().unwrap();
+    save_to_parquet(&reports, parquet_file).unwrap();
+    store.upload(parquet_file, "kafkamion/reports/reports.parquet").await.unwrap();
+
+    let path = store.path("kafkamion/reports/reports.parquet");
+    let stored = load_from_parquet(path.to_str().unwrap()).unwrap();
+    assert_eq!(stored.len(), reports.len());
+    let stored: HashSet<(String, usize)> = stored.into_iter().map(|report| (report.driver_id, report.breaks.len())).collect();
+    let driver_ids: HashSet<(String, usize)> = reports.iter().map(|report| (report.driver_id.clone(), BREAKS)).collect();
+    assert_eq!(stored, driver_ids);
+
+    // erasure of the personal data of a driver
+    let erased = &reports[0].driver_id;
+    let audit = erase_driver(&store, "kafkamion", erased, spool.path()).await.unwrap();
+    let rows = reports.iter().filter(|report| &report.driver_id == erased).count();
+    assert_eq!(audit.objects, [ErasedObject { key: "kafkamion/reports/reports.parquet".to_string(), rows }]);
+    assert_eq!(audit.fields, ["first_name", "last_name", "email", "phone"]);
+    let audits = store.list("kafkamion/erasures/").await.unwrap();
+    assert_eq!(audits.len(), 1);
+
+    let rewritten = load_from_parquet(path.to_str().unwrap()).unwrap();
+    assert_eq!(rewritten.len(), reports.len());
+    for (report, rewritten) in reports.iter().zip(&rewritten) {
+        if &report.driver_id == erased {
+            assert_eq!((&rewritten.first_name, &rewritten.phone), (&String::new(), &String::new()));
+            assert_eq!((&rewritten.email, &rewritten.last_name), (&String::new(), &String::new()));
+        } else {
+            assert_eq!(rewritten, report);
+        }
+    }
+    // the reports already erased are left untouched
+    assert!(erase_driver(&store, "kafkamion", erased, spool.path()).await.unwrap().objects.is_empty());
+}
@@ -0,0 +1,97 @@
+//! Assignments of the drivers to the trucks.
+//!
+//! The `start_day` and `end_day` registrations assign their driver to their truck for the shift,
+//! whatever order they arrive in. A truck in the hands of two drivers at once, a driver on two
+//! trucks at once, and a truck moving with no driver on shift within the position tolerance are
+//! published on the alerts topic. A moving truck is alerted once, on the first position of the
+//! move, after the watermark minus the allowed lateness passed it by the tolerance, so that the
+//! late registrations are taken into account.
+
+use crate::models::time_registration::{TimeRegistration, TimeRegistrationType};
+use serde::{Deserialize, Serialize};
+
+/// Speed above which a truck is moving, in kilometres per hour. Below it, its positions only
+/// drift with the precision of its telematics.
+pub const MOVING_SPEED_KMH: f64 = 5.0;
+
+/// How long an assignment whose `end_day` never arrived is kept, in microseconds
+pub const MAX_SHIFT: i64 = 24 * 3_600_000_000;
+
+/// A driver on shift on a truck, from its `start_day` to its `end_day` in event time, each bound
+/// being unknown while its registration did not arrive
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Assignment {
+    pub driver_id: String,
+    pub truck_id: String,
+    pub start: Option<i64>,
+    pub end: Option<i64>,
+}
+
+impl Assignment {
+    /// Whether both assignments started and share some time
+    pub fn overlaps(&self, other: &Assignment) -> bool {
+        let (Some(start), Some(other_start)) = (self.start, other.start) else {
+            return false;
+        };
+        start < other.end.unwrap_or(i64::MAX) && other_start < self.end.unwrap_or(i64::MAX)
+    }
+
+    /// Whether the driver is on shift at `time`, give or take `tolerance`
+    pub fn covers(&self, time: i64, tolerance: i64) -> bool {
+        self.start.is_some_and(|start| start.saturating_sub(tolerance) <= time)
+            && self.end.is_none_or(|end| time <= end.saturating_add(tolerance))
+    }
+
+    /// Whether the assignment ended before `cutoff`, or started so long before it that its
+    /// `end_day` is not coming
+    pub fn expired(&self, cutoff: i64) -> bool {
+        match (self.start, self.end) {
+            (_, Some(end)) => end < cutoff,
+            (Some(start), None) => start < cutoff.saturating_sub(MAX_SHIFT),
+            (None, None) => true,
+        }
+    }
+}
+
+/// Records a `start_day` or an `end_day` in the assignments of its truck or of its driver,
+/// completing the assignment of its shift when the other bound arrived first. Returns the
+/// assignment, `None` for the other registrations.
+pub fn record(assignments: &mut Vec<Assignment>, registration: &TimeRegistration, time: i64) -> Option<Assignment> {
+    let same = |assignment: &&Assignment| {
+        assignment.driver_id == registration.driver_id && assignment.truck_id == registration.truck_id
+    };
+    let found = match registration.kind {
+        // the nearest end_day after the start_day still missing its start
+        TimeRegistrationType::StartDay => assignments
+            .iter()
+            .enumerate()
+            .filter(|(_, assignment)| same(assignment) && assignment.start.is_none())
+            .filter_map(|(index, assignment)| Some((index, assignment.end.filter(|end| *end >= time)?)))
+            .min_by_key(|(_, end)| *end),
+        // the nearest start_day before the end_day still missing its end
+        TimeRegistrationType::EndDay => assignments
+            .iter()
+            .enumerate()
+            .filter(|(_, assignment)| same(assignment) && assignment.end.is_none())
+            .filter_map(|(index, assignment)| Some((index, assignment.start.filter(|start| *start <= time)?)))
+            .max_by_key(|(_, start)| *start),
+        TimeRegistrationType::StartBreak | TimeRegistrationType::EndBreak => return None,
+    };
+    let index = match found {
+        Some((index, _)) => index,
+        None => {
+            assignments.push(Assignment {
+                driver_id: registration.driver_id.clone(),
+                truck_id: registration.truck_id.clone(),
+                start: None,
+                end: None,
+            });
+            assignments.len() - 1
+        }
+    };
+    match registration.kind {
+        TimeRegistrationType::StartDay => assignments[index].start = Some(time),
+        _ => assignments[index].end = Some(time),
+    }
+    Some(assignments[index].clone())
+}
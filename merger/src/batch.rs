@@ -0,0 +1,91 @@
+use crate::join::{Joiner, Output};
+use crate::models::entity::{Entity, Tombstone};
+use crate::models::report::Report;
+use log::{error, info, warn};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+/// Input files of the batch mode, one JSONL file per topic
+pub struct BatchInput<'a> {
+    pub entities: &'a Path,
+    pub time_registrations: &'a Path,
+    pub positions: &'a Path,
+}
+
+/// A line of the entity dump, an entity or the tombstone deleting one
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum EntityLine {
+    Tombstone(Tombstone),
+    Entity(Entity),
+}
+
+/// Calls `handle` with every record of a JSONL file, skipping the invalid lines
+fn read_jsonl<T: DeserializeOwned>(path: &Path, mut handle: impl FnMut(T)) -> io::Result<()> {
+    let reader = BufReader::new(File::open(path)?);
+    for (index, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str(&line) {
+            Ok(record) => handle(record),
+            Err(err) => error!("Invalid record at {}:{}: {}", path.display(), index + 1, err),
+        }
+    }
+    Ok(())
+}
+
+/// Runs the join on the input files with `joiner`, configured like the streaming one, and writes
+/// the reports as JSONL to `out`. Returns the number of reports written.
+pub fn batch(input: &BatchInput, mut joiner: Joiner, out: &Path) -> io::Result<usize> {
+    let mut reports: Vec<Report> = Vec::new();
+
+    read_jsonl(input.entities, |line| match line {
+        EntityLine::Entity(entity) => reports.extend(joiner.on_entity(entity)),
+        EntityLine::Tombstone(tombstone) => joiner.on_entity_deleted(&tombstone.key),
+    })?;
+    // no partition is tracked, the dumps being complete no event is late
+    let time_registrations = input.time_registrations.display().to_string();
+    read_jsonl(input.time_registrations, |registration| {
+        match joiner.on_time_registration(&time_registrations, registration) {
+            Ok(joined) => reports.extend(joined),
+            Err(rejection) => error!("Skipping a time registration: {}", rejection),
+        }
+    })?;
+    let positions = input.positions.display().to_string();
+    read_jsonl(input.positions, |position| match joiner.on_position(&positions, position) {
+        Ok(joined) => reports.extend(joined),
+        Err(rejection) => error!("Skipping a position: {}", rejection),
+    })?;
+    // every position is known, the registrations without an exact one are located with them
+    reports.extend(joiner.flush());
+    for output in joiner.take_outputs() {
+        match output {
+            Output::Violation(violation) => warn!(
+                "Driver {} breached the {:?} rule from {} to {}",
+                violation.driver_id, violation.rule, violation.period_start, violation.period_end
+            ),
+            Output::Anomaly(anomaly) => warn!(
+                "Skipping the {} of driver {} at {}: {}",
+                anomaly.registration.kind, anomaly.registration.driver_id, anomaly.registration.timestamp, anomaly.reason
+            ),
+            Output::Geofence(event) => info!("Truck {} {:?} geofence {} at {}", event.truck_id, event.kind, event.geofence_id, event.timestamp),
+            Output::Alert(alert) => warn!(
+                "Alert {:?} on trucks {:?} and drivers {:?} at {}",
+                alert.kind, alert.truck_ids, alert.driver_ids, alert.timestamp
+            ),
+        }
+    }
+
+    let mut writer = BufWriter::new(File::create(out)?);
+    for report in &reports {
+        serde_json::to_writer(&mut writer, report)?;
+        writeln!(writer)?;
+    }
+    writer.flush()?;
+    Ok(reports.len())
+}
@@ -0,0 +1,173 @@
+//! Driving hours rules of the Regulation (EC) No 561/2006.
+//!
+//! A finished shift is checked with the working days of its driver from the three previous weeks:
+//! a break after 4.5 hours of driving, at most 9 hours of driving a day or 10 hours twice a week,
+//! 11 hours of rest between two days, at most 56 hours of driving a week and 90 hours in two
+//! consecutive weeks, the weeks starting on Monday at midnight UTC. Each breach is published on
+//! the compliance topic, keyed by `driver_id`, with the limit of its rule and the duration
+//! measured on its period in seconds.
+
+use crate::models::compliance::{Rule, Violation};
+use chrono::{DateTime, Datelike, Days};
+use serde::{Deserialize, Serialize};
+
+const MINUTE: i64 = 60_000_000;
+const HOUR: i64 = 60 * MINUTE;
+const DAY: i64 = 24 * HOUR;
+const WEEK: i64 = 7 * DAY;
+
+/// Driving time after which a break is due
+pub const MAX_DRIVING_BEFORE_BREAK: i64 = 4 * HOUR + 30 * MINUTE;
+pub const BREAK: i64 = 45 * MINUTE;
+/// The break may be split into a first part of 15 minutes followed by a second one of 30
+pub const SPLIT_BREAK_FIRST: i64 = 15 * MINUTE;
+pub const SPLIT_BREAK_SECOND: i64 = 30 * MINUTE;
+pub const DAILY_DRIVING: i64 = 9 * HOUR;
+pub const EXTENDED_DAILY_DRIVING: i64 = 10 * HOUR;
+pub const EXTENDED_DAYS_PER_WEEK: usize = 2;
+pub const DAILY_REST: i64 = 11 * HOUR;
+pub const WEEKLY_DRIVING: i64 = 56 * HOUR;
+pub const FORTNIGHTLY_DRIVING: i64 = 90 * HOUR;
+
+/// How long the working days of a driver are kept to check the rules on the next ones
+pub const HISTORY: i64 = 3 * WEEK;
+
+/// A working day of a driver, from its `start_day` to its `end_day`, in microseconds since the epoch
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WorkDay {
+    pub start: i64,
+    pub end: i64,
+    /// Start and end of each break, in time order
+    pub breaks: Vec<(i64, i64)>,
+}
+
+impl WorkDay {
+    /// Time of the day outside of the breaks
+    pub fn driving(&self) -> i64 {
+        self.end - self.start - self.breaks.iter().map(|(start, end)| end - start).sum::<i64>()
+    }
+}
+
+/// Start of the week of a time, on Monday at midnight UTC
+fn week_start(time: i64) -> i64 {
+    let date = DateTime::from_timestamp_micros(time).unwrap_or_default().date_naive();
+    let monday = date - Days::new(date.weekday().num_days_from_monday() as u64);
+    monday.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc().timestamp_micros()
+}
+
+/// Builds the violation of a rule, converting the durations to seconds
+fn violation(driver_id: &str, rule: Rule, period: (i64, i64), limit: i64, actual: i64) -> Violation {
+    Violation {
+        driver_id: driver_id.to_string(),
+        rule,
+        period_start: period.0.to_string(),
+        period_end: period.1.to_string(),
+        limit: limit / 1_000_000,
+        actual: actual / 1_000_000,
+    }
+}
+
+/// Checks a working day of a driver against the driving hours rules, given the other days of the
+/// driver known so far. A rule on several days is reported once, by the day that breaches it.
+pub fn evaluate(driver_id: &str, day: &WorkDay, history: &[WorkDay]) -> Vec<Violation> {
+    let history: Vec<&WorkDay> = history.iter().filter(|other| other.start != day.start).collect();
+    let mut violations = Vec::new();
+    violations.extend(check_breaks(driver_id, day));
+    violations.extend(check_daily_driving(driver_id, day, &history));
+    violations.extend(check_daily_rest(driver_id, day, &history));
+    violations.extend(check_weekly_driving(driver_id, day, &history));
+    violations.extend(check_fortnightly_driving(driver_id, day, &history));
+    violations
+}
+
+/// Each stretch of driving between two qualifying breaks lasts at most 4.5 hours. A break
+/// qualifies when it lasts 45 minutes, or 30 minutes after a break of 15 minutes.
+fn check_breaks(driver_id: &str, day: &WorkDay) -> Vec<Violation> {
+    let mut violations = Vec::new();
+    let mut driving = 0;
+    let mut stretch_start = day.start;
+    let mut split = false;
+    let mut cursor = day.start;
+    let mut close_stretch = |driving: i64, end: i64, stretch_start: i64| {
+        if driving > MAX_DRIVING_BEFORE_BREAK {
+            violations.push(violation(driver_id, Rule::BreakAfterDriving, (stretch_start, end), MAX_DRIVING_BEFORE_BREAK, driving));
+        }
+    };
+    for &(start, end) in &day.breaks {
+        driving += start - cursor;
+        cursor = end;
+        let length = end - start;
+        if length >= BREAK || (split && length >= SPLIT_BREAK_SECOND) {
+            close_stretch(driving, start, stretch_start);
+            driving = 0;
+            stretch_start = end;
+            split = false;
+        } else if length >= SPLIT_BREAK_FIRST {
+            split = true;
+        }
+    }
+    driving += day.end - cursor;
+    close_stretch(driving, day.end, stretch_start);
+    violations
+}
+
+/// A day has at most 9 hours of driving, or 10 hours at most twice a week
+fn check_daily_driving(driver_id: &str, day: &WorkDay, history: &[&WorkDay]) -> Option<Violation> {
+    let driving = day.driving();
+    if driving > EXTENDED_DAILY_DRIVING {
+        return Some(violation(driver_id, Rule::DailyDriving, (day.start, day.end), EXTENDED_DAILY_DRIVING, driving));
+    }
+    if driving <= DAILY_DRIVING {
+        return None;
+    }
+    let week = week_start(day.start);
+    let extended = history
+        .iter()
+        .filter(|other| week_start(other.start) == week && other.driving() > DAILY_DRIVING)
+        .count();
+    (extended >= EXTENDED_DAYS_PER_WEEK)
+        .then(|| violation(driver_id, Rule::DailyDriving, (day.start, day.end), DAILY_DRIVING, driving))
+}
+
+/// A day is separated from the previous and the next ones by 11 hours of rest
+fn check_daily_rest(driver_id: &str, day: &WorkDay, history: &[&WorkDay]) -> Vec<Violation> {
+    let previous = history.iter().filter(|other| other.end <= day.start).map(|other| other.end).max();
+    let next = history.iter().filter(|other| other.start >= day.end).map(|other| other.start).min();
+    let rests = previous.map(|end| (end, day.start)).into_iter().chain(next.map(|start| (day.end, start)));
+    rests
+        .filter(|(end, start)| start - end < DAILY_REST)
+        .map(|(end, start)| violation(driver_id, Rule::DailyRest, (end, start), DAILY_REST, start - end))
+        .collect()
+}
+
+/// Driving time of the days starting in the weeks starting at `weeks`, the day included
+fn driving_in_weeks(day: &WorkDay, history: &[&WorkDay], weeks: &[i64]) -> i64 {
+    history
+        .iter()
+        .copied()
+        .chain([day])
+        .filter(|other| weeks.contains(&week_start(other.start)))
+        .map(WorkDay::driving)
+        .sum()
+}
+
+/// A week has at most 56 hours of driving
+fn check_weekly_driving(driver_id: &str, day: &WorkDay, history: &[&WorkDay]) -> Option<Violation> {
+    let week = week_start(day.start);
+    let driving = driving_in_weeks(day, history, &[week]);
+    (driving > WEEKLY_DRIVING && driving - day.driving() <= WEEKLY_DRIVING)
+        .then(|| violation(driver_id, Rule::WeeklyDriving, (week, week + WEEK), WEEKLY_DRIVING, driving))
+}
+
+/// Two consecutive weeks have at most 90 hours of driving
+fn check_fortnightly_driving(driver_id: &str, day: &WorkDay, history: &[&WorkDay]) -> Vec<Violation> {
+    let week = week_start(day.start);
+    [week - WEEK, week]
+        .into_iter()
+        .filter_map(|first| {
+            let driving = driving_in_weeks(day, history, &[first, first + WEEK]);
+            (driving > FORTNIGHTLY_DRIVING && driving - day.driving() <= FORTNIGHTLY_DRIVING)
+                .then(|| violation(driver_id, Rule::FortnightlyDriving, (first, first + 2 * WEEK), FORTNIGHTLY_DRIVING, driving))
+        })
+        .collect()
+}
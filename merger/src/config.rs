@@ -6,8 +6,99 @@ use serde::Deserialize;
 #[derive(Deserialize, Debug)]
 pub struct Config {
     pub kafka_broker: String,
-    pub topics: Vec<String>, 
+    pub topics: Topics,
     pub group_id: String,
+    /// Identifies the producer of this instance across restarts, unique to each instance
+    pub transactional_id: String,
+    pub state: StateConfig,
+    pub join: JoinConfig,
+    /// GeoJSON feature collection of the depots and customer sites
+    pub geofences: String,
+}
+
+/// Names of the topics the merger reads from and writes to
+#[derive(Deserialize, Debug)]
+pub struct Topics {
+    pub entity: String,
+    pub time_registration: String,
+    pub position: String,
+    pub report: String,
+    /// Compacted topic where every mutation of the join state is logged
+    pub changelog: String,
+    /// Side output of the events that arrived after the allowed lateness
+    pub late: String,
+    /// Breaches of the driving hours rules
+    pub compliance: String,
+    /// Time registrations out of sequence, left out of the reports
+    pub anomalies: String,
+    /// Trucks entering and leaving the geofences
+    pub geofence: String,
+    /// Conflicts between the assignments of the drivers and the trucks
+    pub alerts: String,
+    /// Requests to erase the personal data of a driver
+    pub erasure: String,
+    /// Records that cannot be joined, with the reason in their headers
+    pub dead_letter: String,
+    /// Topics of the records to join again later, by increasing delay
+    pub retries: Vec<RetryTopic>,
+}
+
+/// Topic where a record waits `delay_secs` before being joined again
+#[derive(Deserialize, Debug, Clone)]
+pub struct RetryTopic {
+    pub topic: String,
+    pub delay_secs: u64,
+}
+
+impl Topics {
+    /// The topics joined together to build the reports
+    pub fn inputs(&self) -> Vec<String> {
+        vec![
+            self.entity.clone(),
+            self.time_registration.clone(),
+            self.position.clone(),
+        ]
+    }
+}
+
+/// Event time settings of the join
+#[derive(Deserialize, Debug)]
+pub struct JoinConfig {
+    /// How far behind the watermark an event may be and still be joined
+    pub allowed_lateness_secs: i64,
+    /// How far in time from a time registration a position may be and still locate it
+    pub position_tolerance_secs: i64,
+    pub position_matching: PositionMatching,
+}
+
+/// How a time registration is located from the positions of its truck
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PositionMatching {
+    /// The position nearest in time
+    Nearest,
+    /// Interpolated between the positions before and after the registration, or the nearest one
+    /// when only one side is within the tolerance
+    Interpolate,
+}
+
+/// Where the join state is kept
+#[derive(Deserialize, Debug)]
+pub struct StateConfig {
+    pub store: StoreKind,
+    /// Directory of the on-disk store
+    pub path: String,
+    /// Longest wait for the next changelog record while restoring the state
+    pub restore_poll_secs: u64,
+    /// Longest restore, the merger failing when the changelog is not read to its end by then
+    pub restore_timeout_secs: u64,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum StoreKind {
+    Memory,
+    Sled,
 }
 
 lazy_static! {
@@ -23,4 +114,4 @@ fn get_config() -> Config {
         .resolve()
         .expect("Config deserialize error");
     config // Return the resolved configuration
-}
\ No newline at end of file
+}
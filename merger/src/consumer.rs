@@ -1,10 +1,56 @@
-use crate::config::CONFIG;
-use log::{error, info};
-use rdkafka::consumer::{BaseConsumer, Consumer};
-use rdkafka::{ClientConfig, Message};
+//! Kafka side of the merger.
+//!
+//! A single consumer reads the input, erasure and retry topics, dispatching each record by topic
+//! to the join. A record that cannot be joined goes to the dead letter topic with its payload and
+//! the `source_topic`, `source_partition`, `source_offset` and `error_reason` headers. One
+//! referencing a driver or a truck not known yet first goes through the retry topics, with the
+//! `retry_attempt` and `retry_at` headers, a retry partition being paused until its next record
+//! is due.
+//!
+//! The records are joined exactly once, in transactions committing the reports, the changelog
+//! writes, the side records and the consumed offsets together. The state is rebuilt from the
+//! committed changelog on startup and when partitions the merger never owned are assigned to it.
+//! A failed transaction stops the merger, which rebuilds its whole store on the next startup,
+//! while a clean stop leaves a checkpoint of the changelog offsets next to the `sled` store so
+//! that only the changelog after them is replayed.
+
+use crate::config::{RetryTopic, Topics, CONFIG};
+use crate::geofence::GeofenceRegistry;
+use crate::join::{Joiner, Output, Rejection};
+use crate::models::alert::AlertType;
+use crate::models::report::Report;
+use crate::state;
+use crate::state::{ChangelogStore, Checkpoint, StateStore};
+use kafkamion_broker::{BrokerError, Record, Sink, Source};
+use log::{error, info, warn};
+use rdkafka::client::ClientContext;
+use rdkafka::consumer::{BaseConsumer, Consumer, ConsumerContext, ConsumerGroupMetadata, Rebalance, StreamConsumer};
+use rdkafka::error::KafkaResult;
+use rdkafka::message::{BorrowedMessage, Header, Headers, OwnedHeaders};
+use rdkafka::producer::{BaseRecord, DefaultProducerContext, Producer, ThreadedProducer};
+use rdkafka::{ClientConfig, Message, Offset, TopicPartitionList};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+use std::fmt::{self, Display};
+use std::process::ExitCode;
 use std::sync::{Arc, Mutex};
-use std::thread;
 use std::time::Duration;
+use tokio::task::block_in_place;
+use tokio::time::Instant;
+
+/// Most records joined in one transaction
+const MAX_TRANSACTION_RECORDS: usize = 500;
+/// How long a transaction waits for more records once it received one
+const BATCH_LINGER: Duration = Duration::from_millis(100);
+const TRANSACTION_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Headers of the records sent to the retry and dead letter topics
+const SOURCE_TOPIC: &str = "source_topic";
+const SOURCE_PARTITION: &str = "source_partition";
+const SOURCE_OFFSET: &str = "source_offset";
+const ERROR_REASON: &str = "error_reason";
+const RETRY_ATTEMPT: &str = "retry_attempt";
+/// Time in epoch milliseconds before which a retried record is not joined
+const RETRY_AT: &str = "retry_at";
 
 /// Checks if Kafka is available by attempting to connect to the server.
 /// Returns `true` if the connection is successful, otherwise `false`.
@@ -26,60 +72,763 @@ pub fn is_kafka_available(client_config: &ClientConfig) -> bool {
     }
 }
 
-pub fn consumer(client_config: ClientConfig) {
-    // Log the configuration
-    info!("Configuration: {:#?}", client_config);
+fn to_record(message: &BorrowedMessage) -> Record {
+    Record {
+        topic: message.topic().to_string(),
+        partition: message.partition(),
+        offset: message.offset(),
+        key: message.key().map(|key| String::from_utf8_lossy(key).into_owned()),
+        payload: message.payload().map(<[u8]>::to_vec),
+        headers: message
+            .headers()
+            .map(|headers| {
+                headers
+                    .iter()
+                    .map(|header| {
+                        let value = header.value.map(String::from_utf8_lossy).unwrap_or_default();
+                        (header.key.to_string(), value.into_owned())
+                    })
+                    .collect()
+            })
+            .unwrap_or_default(),
+    }
+}
 
-    if !is_kafka_available(&client_config) {
-        error!("Kafka is not available. Exiting...");
-        return;
-    }
-
-    // Shared resource for logging or data processing
-    let shared_resource = Arc::new(Mutex::new(Vec::new()));
-
-    // List of topics to subscribe to
-    let topics = vec!["topic1", "topic2", "topic3"];
-
-    // Spawn a thread for each topic
-    for topic in &CONFIG.topics {
-        let client_config = client_config.clone();
-        let shared_resource = Arc::clone(&shared_resource);
-        let topic = topic.clone(); // Cloner le sujet pour le thread
-    
-        thread::spawn(move || {
-            let consumer: BaseConsumer = client_config.create().expect("Consumer creation failed");
-    
-            consumer
-                .subscribe(&[&topic])
-                .expect("Subscription to topic failed");
-    
-            loop {
-                match consumer.poll(Duration::from_millis(1000)) {
-                    Some(Ok(message)) => {
-                        if let Some(payload) = message.payload() {
-                            let payload_str = String::from_utf8_lossy(payload);
-                            {
-                                let mut resource = shared_resource.lock().unwrap();
-                                resource.push(format!("Topic: {}, Message: {}", topic, payload_str));
-                            }
-                            println!("Received from {}: {}", topic, payload_str);
-                        }
-                    }
-                    Some(Err(err)) => {
-                        eprintln!("Error in topic {}: {:?}", topic, err);
+/// Records of a topic from the given offsets, or from its beginning, up to the end offsets it had
+/// when the reading started
+struct ChangelogSource {
+    consumer: BaseConsumer,
+    /// Offset following the last record of each partition still to read
+    ends: HashMap<i32, i64>,
+    /// Offset following the last record read from each partition
+    read: BTreeMap<i32, i64>,
+    /// When the reading gives up on the partitions not read to their end
+    deadline: std::time::Instant,
+}
+
+impl ChangelogSource {
+    fn new(client_config: &ClientConfig, topic: &str, from: &BTreeMap<i32, i64>, timeout: Duration) -> KafkaResult<Self> {
+        let deadline = std::time::Instant::now() + timeout;
+        let consumer: BaseConsumer = client_config.clone().set("enable.auto.commit", "false").create()?;
+        let metadata = consumer.fetch_metadata(Some(topic), Duration::from_secs(10))?;
+        let mut assignment = TopicPartitionList::new();
+        let mut ends = HashMap::new();
+        let mut read = BTreeMap::new();
+        for partition in metadata.topics().iter().flat_map(|topic| topic.partitions()) {
+            let (low, high) = consumer.fetch_watermarks(topic, partition.id(), Duration::from_secs(10))?;
+            // the records before the low watermark were compacted away
+            let start = from.get(&partition.id()).copied().unwrap_or(low).max(low);
+            if high > start {
+                assignment.add_partition_offset(topic, partition.id(), Offset::Offset(start))?;
+                ends.insert(partition.id(), high);
+            }
+            read.insert(partition.id(), start);
+        }
+        consumer.assign(&assignment)?;
+        Ok(Self { consumer, ends, read, deadline })
+    }
+
+    /// Moves past the partitions whose position reached their end: the last offset of a
+    /// partition may be the commit marker of a transaction, which is never delivered
+    fn skip_markers(&mut self) {
+        let Ok(positions) = self.consumer.position() else {
+            return;
+        };
+        for element in positions.elements() {
+            if let Offset::Offset(position) = element.offset() {
+                self.read.insert(element.partition(), position);
+                if self.ends.get(&element.partition()).is_some_and(|end| position >= *end) {
+                    self.ends.remove(&element.partition());
+                }
+            }
+        }
+    }
+}
+
+impl Source for ChangelogSource {
+    /// Next record, polling again when none arrives within `timeout` until every partition is
+    /// read to its end or the deadline passes
+    fn poll(&mut self, timeout: Duration) -> Option<Result<Record, BrokerError>> {
+        while !self.ends.is_empty() && std::time::Instant::now() < self.deadline {
+            match self.consumer.poll(timeout) {
+                Some(Ok(message)) => {
+                    self.read.insert(message.partition(), message.offset() + 1);
+                    if self.ends.get(&message.partition()).is_some_and(|end| message.offset() + 1 >= *end) {
+                        self.ends.remove(&message.partition());
                     }
-                    None => {
-                        println!("No message received from topic: {}", topic);
+                    return Some(Ok(to_record(&message)));
+                }
+                Some(Err(err)) => return Some(Err(BrokerError(format!("{:?}", err)))),
+                None => {
+                    self.skip_markers();
+                    if !self.ends.is_empty() {
+                        info!("Waiting for the changelog, {} partitions left to read", self.ends.len());
                     }
                 }
             }
+        }
+        None
+    }
+}
+
+/// Replays the changelog topic into the join state from the offsets already `applied`, which
+/// are moved past the records read. Fails when the changelog is not read to the end offsets it
+/// had when the restore started within `state.restore_timeout_secs`, the state being partial.
+fn restore(client_config: &ClientConfig, joiner: &Mutex<Joiner>, applied: &mut BTreeMap<i32, i64>) -> Result<(), BrokerError> {
+    let topic = &CONFIG.topics.changelog;
+    let timeout = Duration::from_secs(CONFIG.state.restore_timeout_secs);
+    let mut source = ChangelogSource::new(client_config, topic, applied, timeout)
+        .map_err(|err| BrokerError(format!("unable to read the changelog {}: {:?}", topic, err)))?;
+    let count = joiner.lock().unwrap().restore(&mut source, Duration::from_secs(CONFIG.state.restore_poll_secs));
+    if !source.ends.is_empty() {
+        return Err(BrokerError(format!(
+            "changelog {} not read to its end within {}s, {} partitions left",
+            topic,
+            timeout.as_secs(),
+            source.ends.len()
+        )));
+    }
+    applied.extend(source.read);
+    info!("Join state restored from {} changelog records", count);
+    Ok(())
+}
+
+/// Rebuilds the join state when partitions this instance never owned are assigned to it, their
+/// previous owner having logged its state to the changelog. The restore blocks the consumer so
+/// that no record of these partitions is joined on a stale state.
+struct RebalanceContext {
+    joiner: Arc<Mutex<Joiner>>,
+    client_config: ClientConfig,
+    /// Partitions assigned to the consumer since it started, `None` before the first assignment
+    owned: Mutex<Option<HashSet<(String, i32)>>>,
+    /// Offset following the last changelog record applied to the join state, by partition
+    applied: Mutex<BTreeMap<i32, i64>>,
+    /// Partitions assigned when the join state was checkpointed
+    checkpointed: Option<BTreeSet<(String, i32)>>,
+    /// Why the join state could not be restored on an assignment, nothing being joined then
+    failure: Mutex<Option<BrokerError>>,
+}
+
+impl RebalanceContext {
+    fn restore(&self, applied: &mut BTreeMap<i32, i64>) {
+        if let Err(err) = restore(&self.client_config, &self.joiner, applied) {
+            error!("Unable to restore the join state: {}", err);
+            self.failure.lock().unwrap().get_or_insert(err);
+        }
+    }
+}
+
+impl ClientContext for RebalanceContext {}
+
+impl ConsumerContext for RebalanceContext {
+    fn post_rebalance(&self, rebalance: &Rebalance) {
+        let assignment = match rebalance {
+            Rebalance::Assign(assignment) => assignment,
+            Rebalance::Revoke(revoked) => {
+                let mut joiner = self.joiner.lock().unwrap();
+                for element in revoked.elements() {
+                    joiner.untrack(&partition_key(element.topic(), element.partition()));
+                }
+                return;
+            }
+            Rebalance::Error(_) => return,
+        };
+        let assigned: HashSet<(String, i32)> = assignment
+            .elements()
+            .iter()
+            .map(|element| (element.topic().to_string(), element.partition()))
+            .collect();
+
+        // the entities have no event time and the retried events are late already, they do not
+        // hold back the watermark
+        {
+            let mut joiner = self.joiner.lock().unwrap();
+            let timed = [&CONFIG.topics.time_registration, &CONFIG.topics.position];
+            for (topic, partition) in assigned.iter().filter(|(topic, _)| timed.contains(&topic)) {
+                joiner.track(&partition_key(topic, *partition));
+            }
+        }
+        let mut owned = self.owned.lock().unwrap();
+        let mut applied = self.applied.lock().unwrap();
+        match owned.as_mut() {
+            // the state was restored on startup, from the checkpoint when there was one
+            None => {
+                let sorted: BTreeSet<(String, i32)> = assigned.iter().cloned().collect();
+                if self.checkpointed.as_ref().is_some_and(|checkpointed| *checkpointed != sorted) {
+                    info!("Partitions assigned differ from the checkpoint, rebuilding the join state");
+                    self.joiner.lock().unwrap().clear();
+                    applied.clear();
+                    self.restore(&mut applied);
+                }
+                *owned = Some(assigned);
+            }
+            Some(owned) => {
+                if !assigned.is_subset(owned) {
+                    info!("New partitions assigned, restoring the join state");
+                    owned.extend(assigned);
+                    self.restore(&mut applied);
+                }
+            }
+        }
+    }
+}
+
+/// Records received to be joined in one transaction
+struct Batch(VecDeque<Record>);
+
+impl Source for Batch {
+    fn poll(&mut self, _timeout: Duration) -> Option<Result<Record, BrokerError>> {
+        self.0.pop_front().map(Ok)
+    }
+}
+
+/// Receives up to `max_records` records, waiting for the first one until `until` when given,
+/// then for the next ones up to `BATCH_LINGER`
+async fn recv_batch(consumer: &StreamConsumer<RebalanceContext>, max_records: usize, until: Option<Instant>) -> Vec<Record> {
+    let mut records = Vec::new();
+    let mut deadline = until;
+    while records.len() < max_records {
+        let received = match deadline {
+            Some(deadline) => match tokio::time::timeout_at(deadline, consumer.recv()).await {
+                Ok(received) => received,
+                Err(_) => break,
+            },
+            None => consumer.recv().await,
+        };
+        match received {
+            Ok(message) => records.push(to_record(&message)),
+            Err(err) => error!("Error while consuming: {:?}", err),
+        }
+        let linger = Instant::now() + BATCH_LINGER;
+        deadline = Some(deadline.map_or(linger, |deadline| deadline.min(linger)));
+    }
+    records
+}
+
+/// Offsets following the last record of each partition of the batch
+fn next_offsets(records: &[Record]) -> KafkaResult<TopicPartitionList> {
+    let mut next: HashMap<(&str, i32), i64> = HashMap::new();
+    for record in records {
+        let offset = next.entry((&record.topic, record.partition)).or_default();
+        *offset = (*offset).max(record.offset + 1);
+    }
+    let mut offsets = TopicPartitionList::new();
+    for ((topic, partition), offset) in next {
+        offsets.add_partition_offset(topic, partition, Offset::Offset(offset))?;
+    }
+    Ok(offsets)
+}
+
+/// Records of the retry topics that are not due yet, by partition. A partition is paused while
+/// it has held records, so that its records are joined in order.
+#[derive(Default)]
+struct Pending(HashMap<(String, i32), VecDeque<Record>>);
+
+impl Pending {
+    /// Holds the record if it is not due or follows a held record, otherwise gives it back
+    fn hold(&mut self, record: Record) -> Option<Record> {
+        let partition = (record.topic.clone(), record.partition);
+        match self.0.get_mut(&partition) {
+            Some(held) => held.push_back(record),
+            None if retry_delay(&record).is_some() => {
+                self.0.insert(partition, VecDeque::from([record]));
+            }
+            None => return Some(record),
+        }
+        None
+    }
+
+    /// Takes the held records that are due, in order
+    fn take_due(&mut self) -> Vec<Record> {
+        let mut due = Vec::new();
+        for held in self.0.values_mut() {
+            while held.front().is_some_and(|record| retry_delay(record).is_none()) {
+                due.extend(held.pop_front());
+            }
+        }
+        self.0.retain(|_, held| !held.is_empty());
+        due
+    }
+
+    /// When the first held record is due
+    fn next_due(&self) -> Option<Instant> {
+        self.0
+            .values()
+            .filter_map(VecDeque::front)
+            .map(|record| Instant::now() + retry_delay(record).unwrap_or_default())
+            .min()
+    }
+
+    fn partitions(&self) -> HashSet<(String, i32)> {
+        self.0.keys().cloned().collect()
+    }
+
+    /// Drops the records of the partitions no longer assigned, their new owner reading them again
+    fn retain(&mut self, assigned: &HashSet<(String, i32)>) {
+        self.0.retain(|partition, _| assigned.contains(partition));
+    }
+}
+
+fn partition_list<'a>(partitions: impl IntoIterator<Item = &'a (String, i32)>) -> TopicPartitionList {
+    let mut list = TopicPartitionList::new();
+    for (topic, partition) in partitions {
+        list.add_partition(topic, *partition);
+    }
+    list
+}
+
+/// Transactional Kafka producer of the reports, the changelog and the side outputs
+struct KafkaSink(ThreadedProducer<DefaultProducerContext>);
+
+impl KafkaSink {
+    fn new(transactional_id: &str) -> KafkaResult<Self> {
+        let producer: ThreadedProducer<DefaultProducerContext> = ClientConfig::new()
+            .set("bootstrap.servers", &CONFIG.kafka_broker)
+            .set("transactional.id", transactional_id)
+            .create()?;
+        producer.init_transactions(TRANSACTION_TIMEOUT)?;
+        Ok(Self(producer))
+    }
+
+    /// Joins records in one transaction, which commits the records produced by the join, the
+    /// changelog included, together with the offsets following the records for `group`. The
+    /// transaction is aborted when one of these records cannot be sent.
+    fn join_batch(&self, group: &ConsumerGroupMetadata, records: Vec<Record>, joiner: &Mutex<Joiner>) -> Result<(), BrokerError> {
+        let kafka = |err| BrokerError(format!("{:?}", err));
+        let offsets = next_offsets(&records).map_err(kafka)?;
+        self.0.begin_transaction().map_err(kafka)?;
+        let committed = pump(&mut Batch(records.into()), self, joiner, &CONFIG.topics, Duration::ZERO).and_then(|_| {
+            self.0
+                .send_offsets_to_transaction(&offsets, group, TRANSACTION_TIMEOUT)
+                .and_then(|()| self.0.commit_transaction(TRANSACTION_TIMEOUT))
+                .map_err(kafka)
         });
+        if committed.is_err() {
+            if let Err(err) = self.0.abort_transaction(TRANSACTION_TIMEOUT) {
+                error!("Unable to abort the transaction: {:?}", err);
+            }
+        }
+        committed
+    }
+}
+
+impl Sink for KafkaSink {
+    fn send_with_headers(&self, topic: &str, key: &str, payload: &[u8], headers: &[(&str, &str)]) -> Result<(), BrokerError> {
+        let mut record = BaseRecord::to(topic).key(key).payload(payload);
+        if !headers.is_empty() {
+            let headers = headers.iter().fold(OwnedHeaders::new(), |owned, (name, value)| {
+                owned.insert(Header { key: name, value: Some(*value) })
+            });
+            record = record.headers(headers);
+        }
+        self.0.send(record).map_err(|(err, _)| BrokerError(format!("{:?}", err)))
+    }
+
+    fn send_tombstone(&self, topic: &str, key: &str) -> Result<(), BrokerError> {
+        let record: BaseRecord<str, [u8]> = BaseRecord::to(topic).key(key);
+        self.0.send(record).map_err(|(err, _)| BrokerError(format!("{:?}", err)))
+    }
+}
+
+/// Identifies an input partition in the watermarks of the join
+pub fn partition_key(topic: &str, partition: i32) -> String {
+    format!("{}/{}", topic, partition)
+}
+
+/// Why a record was not joined
+#[derive(Debug)]
+pub enum ProcessError {
+    Invalid(serde_json::Error),
+    Rejected(Rejection),
+}
+
+impl Display for ProcessError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProcessError::Invalid(err) => write!(f, "{}", err),
+            ProcessError::Rejected(rejection) => write!(f, "{}", rejection),
+        }
+    }
+}
+
+impl ProcessError {
+    /// Reason given in the headers of the record sent to the dead letter topic
+    fn reason(&self) -> String {
+        match self {
+            ProcessError::Invalid(err) if err.is_data() => format!("schema violation: {}", err),
+            ProcessError::Invalid(err) => format!("malformed JSON: {}", err),
+            ProcessError::Rejected(rejection @ Rejection::InvalidTimestamp(_)) => format!("schema violation: {}", rejection),
+            ProcessError::Rejected(rejection) => rejection.to_string(),
+        }
+    }
+}
+
+impl From<serde_json::Error> for ProcessError {
+    fn from(err: serde_json::Error) -> Self {
+        ProcessError::Invalid(err)
+    }
+}
+
+impl From<Rejection> for ProcessError {
+    fn from(rejection: Rejection) -> Self {
+        ProcessError::Rejected(rejection)
+    }
+}
+
+/// Deserializes a payload according to the topic it comes from and feeds it to the join
+pub fn process(joiner: &mut Joiner, topics: &Topics, record: &Record, payload: &[u8]) -> Result<Vec<Report>, ProcessError> {
+    let partition = partition_key(&record.topic, record.partition);
+    if record.topic == topics.entity {
+        Ok(joiner.on_entity(serde_json::from_slice(payload)?))
+    } else if record.topic == topics.erasure {
+        joiner.on_erasure(serde_json::from_slice(payload)?);
+        Ok(Vec::new())
+    } else if record.topic == topics.time_registration {
+        Ok(joiner.on_time_registration(&partition, serde_json::from_slice(payload)?)?)
+    } else {
+        Ok(joiner.on_position(&partition, serde_json::from_slice(payload)?)?)
+    }
+}
+
+/// Publishes a violation on the compliance topic or an anomaly on the anomalies topic, keyed by
+/// its driver, a geofence event on the geofence topic, keyed by its truck, or an alert on the
+/// alerts topic, keyed by the driver on two trucks or by the truck
+fn publish_output(sink: &dyn Sink, topics: &Topics, output: &Output) -> Result<(), BrokerError> {
+    let (topic, key, payload) = match output {
+        Output::Violation(violation) => {
+            warn!("Driver {} breached the {:?} rule", violation.driver_id, violation.rule);
+            (&topics.compliance, &violation.driver_id, serde_json::to_string(violation))
+        }
+        Output::Anomaly(anomaly) => {
+            warn!("Anomaly of driver {}: {}", anomaly.registration.driver_id, anomaly.reason);
+            (&topics.anomalies, &anomaly.registration.driver_id, serde_json::to_string(anomaly))
+        }
+        Output::Geofence(event) => {
+            info!("Truck {} {:?} geofence {}", event.truck_id, event.kind, event.geofence_id);
+            (&topics.geofence, &event.truck_id, serde_json::to_string(event))
+        }
+        Output::Alert(alert) => {
+            warn!("Alert {:?} on trucks {:?} and drivers {:?}", alert.kind, alert.truck_ids, alert.driver_ids);
+            let key = match alert.kind {
+                AlertType::DriverOnTwoTrucks => alert.driver_ids.first(),
+                AlertType::DoubleBookedTruck | AlertType::TruckMovingWithoutDriver => alert.truck_ids.first(),
+            };
+            (&topics.alerts, key.unwrap_or(&alert.timestamp), serde_json::to_string(alert))
+        }
+    };
+    let payload = payload.expect("Output serialization failed");
+    sink.send(topic, key, payload.as_bytes())
+        .map_err(|err| BrokerError(format!("unable to send to {} the record keyed {}: {}", topic, key, err)))
+}
+
+/// Publishes a report on the report topic, keyed by its driver
+fn publish(sink: &dyn Sink, topics: &Topics, report: &Report) -> Result<(), BrokerError> {
+    let payload = serde_json::to_string(report).expect("Report serialization failed");
+    sink.send(&topics.report, &report.driver_id, payload.as_bytes())
+        .map_err(|err| BrokerError(format!("unable to send the report of driver {}: {}", report.driver_id, err)))?;
+    info!("Report sent for driver {}", report.driver_id);
+    Ok(())
+}
+
+/// Sends a record that was not joined to `topic`, with its origin, the reason and the number of
+/// attempts made to join it in its headers. A retried record also gets the time it is due.
+fn divert(sink: &dyn Sink, topic: &str, record: &Record, payload: &[u8], reason: &str, attempt: usize, retry_at: Option<i64>) -> Result<(), BrokerError> {
+    let partition = record.partition.to_string();
+    let offset = record.offset.to_string();
+    let attempt = attempt.to_string();
+    let retry_at = retry_at.map(|at| at.to_string());
+    let mut headers = vec![
+        (SOURCE_TOPIC, record.topic.as_str()),
+        (SOURCE_PARTITION, partition.as_str()),
+        (SOURCE_OFFSET, offset.as_str()),
+        (ERROR_REASON, reason),
+        (RETRY_ATTEMPT, attempt.as_str()),
+    ];
+    if let Some(retry_at) = &retry_at {
+        headers.push((RETRY_AT, retry_at));
+    }
+    let key = record.key.as_deref().unwrap_or_default();
+    sink.send_with_headers(topic, key, payload, &headers).map_err(|err| {
+        BrokerError(format!("unable to send the record of topic {} at offset {} to {}: {}", record.topic, record.offset, topic, err))
+    })
+}
+
+/// The original record carried by a record of a retry topic and the number of attempts made to
+/// join it. `None` if its headers are missing.
+fn retried(record: &Record) -> Option<(Record, usize)> {
+    let original = Record {
+        topic: record.header(SOURCE_TOPIC)?.to_string(),
+        partition: record.header(SOURCE_PARTITION)?.parse().ok()?,
+        offset: record.header(SOURCE_OFFSET)?.parse().ok()?,
+        key: record.key.clone(),
+        payload: record.payload.clone(),
+        headers: Vec::new(),
+    };
+    let attempt = record.header(RETRY_ATTEMPT)?.parse().ok()?;
+    Some((original, attempt))
+}
+
+/// Time left before a record of a retry topic is due, `None` once it is
+fn retry_delay(record: &Record) -> Option<Duration> {
+    let retry_at: i64 = record.header(RETRY_AT)?.parse().ok()?;
+    let wait = retry_at - chrono::Utc::now().timestamp_millis();
+    (wait > 0).then(|| Duration::from_millis(wait as u64))
+}
+
+/// Feeds the records of the source to the join and publishes the reports to the sink, until no
+/// record arrives within `timeout`. Returns the number of records read.
+///
+/// The records referencing a driver or a truck not known yet go to the next retry topic, the
+/// other records that cannot be joined to the dead letter topic. The records read from a retry
+/// topic are joined as if read from their original topic, the caller waiting for them to be due.
+///
+/// Stops at the first record that cannot be sent, the changelog included, returning the error:
+/// the records read so far must not be committed then.
+pub fn pump(source: &mut dyn Source, sink: &dyn Sink, joiner: &Mutex<Joiner>, topics: &Topics, timeout: Duration) -> Result<usize, BrokerError> {
+    let mut count = 0;
+    while let Some(received) = source.poll(timeout) {
+        count += 1;
+        match received {
+            Ok(record) => join_record(record, sink, joiner, topics)?,
+            Err(err) => error!("Error while consuming: {}", err),
+        }
+        if let Some(err) = joiner.lock().unwrap().take_failure() {
+            return Err(err);
+        }
+    }
+    Ok(count)
+}
+
+/// Joins a record and sends what it produced
+fn join_record(mut record: Record, sink: &dyn Sink, joiner: &Mutex<Joiner>, topics: &Topics) -> Result<(), BrokerError> {
+    if record.payload.is_none() {
+        // a tombstone of the entity topic deletes its driver or truck
+        if record.topic == topics.entity {
+            match record.key.as_deref() {
+                Some(key) => joiner.lock().unwrap().on_entity_deleted(key),
+                None => warn!("Tombstone without key in topic {} at offset {}", record.topic, record.offset),
+            }
+        }
+        return Ok(());
+    }
+    let mut attempt = 0;
+    if topics.retries.iter().any(|retry| retry.topic == record.topic) {
+        match retried(&record) {
+            Some((original, attempts)) => (record, attempt) = (original, attempts),
+            None => {
+                let payload = record.payload.as_deref().unwrap_or_default();
+                return divert(sink, &topics.dead_letter, &record, payload, "missing retry headers", 0, None);
+            }
+        }
+    }
+    let Some(payload) = record.payload.as_deref() else {
+        return Ok(());
+    };
+    let (reports, outputs) = {
+        let mut joiner = joiner.lock().unwrap();
+        let reports = process(&mut joiner, topics, &record, payload);
+        (reports, joiner.take_outputs())
+    };
+    for output in &outputs {
+        publish_output(sink, topics, output)?;
+    }
+    match reports {
+        Ok(reports) => {
+            for report in &reports {
+                publish(sink, topics, report)?;
+            }
+            Ok(())
+        }
+        Err(ProcessError::Rejected(rejection @ Rejection::Late { .. })) => {
+            warn!("Late record in topic {} at offset {}: {}", record.topic, record.offset, rejection);
+            let key = record.key.as_deref().unwrap_or_default();
+            sink.send(&topics.late, key, payload)
+                .map_err(|err| BrokerError(format!("unable to send the late record to {}: {}", topics.late, err)))
+        }
+        Err(ProcessError::Rejected(rejection)) if rejection.is_transient() && attempt < topics.retries.len() => {
+            let RetryTopic { topic, delay_secs } = &topics.retries[attempt];
+            warn!("Retrying the record of topic {} at offset {} in {}s: {}", record.topic, record.offset, delay_secs, rejection);
+            let retry_at = chrono::Utc::now().timestamp_millis() + *delay_secs as i64 * 1000;
+            divert(sink, topic, &record, payload, &rejection.to_string(), attempt + 1, Some(retry_at))
+        }
+        Err(err) => {
+            error!(
+                "Invalid message in topic {}: {} ({})",
+                record.topic,
+                String::from_utf8_lossy(payload),
+                err
+            );
+            divert(sink, &topics.dead_letter, &record, payload, &err.reason(), attempt, None)
+        }
+    }
+}
+
+/// Joins the input topics and the retry topics with a single consumer, in transactions, until
+/// Ctrl-C is received or a transaction fails
+pub async fn consumer(mut client_config: ClientConfig) -> ExitCode {
+    // Log the configuration
+    info!("Configuration: {:#?}", client_config);
+
+    if !is_kafka_available(&client_config) {
+        error!("Kafka is not available. Exiting...");
+        return ExitCode::FAILURE;
+    }
+
+    // The offsets are committed by the transactions, which the consumer only reads once committed
+    client_config
+        .set("enable.auto.commit", "false")
+        .set("isolation.level", "read_committed");
+
+    let geofences = match GeofenceRegistry::load(&CONFIG.geofences) {
+        Ok(geofences) => geofences,
+        Err(err) => {
+            error!("Unable to load the geofences: {}", err);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let sink = match KafkaSink::new(&CONFIG.transactional_id) {
+        Ok(sink) => Arc::new(sink),
+        Err(err) => {
+            error!("Unable to create the transactional producer: {:?}", err);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    // Join state logged to the changelog. Without the checkpoint of a clean stop, the store may
+    // hold the effects of a transaction that was not committed, so it is rebuilt from the whole
+    // committed changelog. With it, only the changelog records after the checkpoint are replayed.
+    let checkpoint = match Checkpoint::take(&CONFIG.state) {
+        Ok(checkpoint) => checkpoint,
+        Err(err) => {
+            error!("Unable to take the checkpoint of the join state: {}", err);
+            return ExitCode::FAILURE;
+        }
+    };
+    let mut store = ChangelogStore::new(state::open(&CONFIG.state), sink.clone(), &CONFIG.topics.changelog);
+    if checkpoint.is_none() {
+        store.clear();
     }
-    
+    let joiner = Joiner::with_store(Box::new(store))
+        .with_config(&CONFIG.join)
+        .with_geofences(geofences);
+    let joiner = Arc::new(Mutex::new(joiner));
+    let (mut applied, checkpointed) = checkpoint.map(|checkpoint| (checkpoint.offsets, Some(checkpoint.assigned))).unwrap_or_default();
+    if let Err(err) = block_in_place(|| restore(&client_config, &joiner, &mut applied)) {
+        error!("Unable to restore the join state: {}", err);
+        return ExitCode::FAILURE;
+    }
+
+    let context = RebalanceContext {
+        joiner: Arc::clone(&joiner),
+        client_config: client_config.clone(),
+        owned: Mutex::new(None),
+        applied: Mutex::new(applied),
+        checkpointed,
+        failure: Mutex::new(None),
+    };
+    let consumer: StreamConsumer<RebalanceContext> = client_config
+        .create_with_context(context)
+        .expect("Consumer creation failed");
+    let mut topics = CONFIG.topics.inputs();
+    topics.push(CONFIG.topics.erasure.clone());
+    topics.extend(CONFIG.topics.retries.iter().map(|retry| retry.topic.clone()));
+    let topics: Vec<&str> = topics.iter().map(String::as_str).collect();
+    consumer.subscribe(&topics).expect("Subscription to topics failed");
 
-    // Prevent main thread from exiting
+    let mut pending = Pending::default();
+    let interrupted = tokio::signal::ctrl_c();
+    tokio::pin!(interrupted);
     loop {
-        thread::park();
+        let received = tokio::select! {
+            received = recv_batch(&consumer, MAX_TRANSACTION_RECORDS, pending.next_due()) => received,
+            _ = &mut interrupted => {
+                info!("Interrupted, stopping the consumer");
+                return save_checkpoint(&consumer, &joiner);
+            }
+        };
+        if let Some(err) = consumer.context().failure.lock().unwrap().take() {
+            error!("Exiting on a partial join state, nothing joined: {}", err);
+            return ExitCode::FAILURE;
+        }
+
+        // the records of the partitions revoked meanwhile are read again by their new owner
+        let assigned: HashSet<(String, i32)> = match consumer.assignment() {
+            Ok(assignment) => assignment
+                .elements()
+                .iter()
+                .map(|element| (element.topic().to_string(), element.partition()))
+                .collect(),
+            Err(err) => {
+                error!("Unable to read the assignment: {:?}", err);
+                continue;
+            }
+        };
+        pending.retain(&assigned);
+        let paused = pending.partitions();
+
+        // dispatch by topic: the records of the retry topics wait to be due, the others are joined
+        let mut records = pending.take_due();
+        for record in received {
+            if !assigned.contains(&(record.topic.clone(), record.partition)) {
+                continue;
+            }
+            if CONFIG.topics.retries.iter().any(|retry| retry.topic == record.topic) {
+                records.extend(pending.hold(record));
+            } else {
+                records.push(record);
+            }
+        }
+
+        let held = pending.partitions();
+        if held != paused {
+            if let Err(err) = consumer.pause(&partition_list(held.difference(&paused))) {
+                error!("Unable to pause the retry partitions: {:?}", err);
+            }
+            if let Err(err) = consumer.resume(&partition_list(paused.difference(&held))) {
+                error!("Unable to resume the retry partitions: {:?}", err);
+            }
+        }
+
+        if records.is_empty() {
+            continue;
+        }
+        let group = consumer.group_metadata().expect("Consumer without group");
+        if let Err(err) = block_in_place(|| sink.join_batch(&group, records, &joiner)) {
+            // the join state holds the effects of the failed transaction, a restart rebuilds it
+            error!("Transaction failed, exiting to resume from the last committed one: {:?}", err);
+            return ExitCode::FAILURE;
+        }
+    }
+}
+
+/// Checkpoints the join state on a clean stop. No transaction being in flight, the state holds
+/// the changelog up to the offsets applied and the changes of the committed transactions, which
+/// are logged after these offsets, so a restart only replays the changelog from them.
+fn save_checkpoint(consumer: &StreamConsumer<RebalanceContext>, joiner: &Mutex<Joiner>) -> ExitCode {
+    if let Some(err) = consumer.context().failure.lock().unwrap().take() {
+        error!("The join state is partial, it is not checkpointed: {}", err);
+        return ExitCode::FAILURE;
+    }
+    let assigned = match consumer.assignment() {
+        Ok(assignment) => assignment
+            .elements()
+            .iter()
+            .map(|element| (element.topic().to_string(), element.partition()))
+            .collect(),
+        Err(err) => {
+            error!("Unable to read the assignment, the join state is not checkpointed: {:?}", err);
+            return ExitCode::SUCCESS;
+        }
+    };
+    let checkpoint = Checkpoint {
+        offsets: consumer.context().applied.lock().unwrap().clone(),
+        assigned,
+    };
+    joiner.lock().unwrap().persist();
+    match checkpoint.save(&CONFIG.state) {
+        Ok(()) => info!("Join state checkpointed at the changelog offsets {:?}", checkpoint.offsets),
+        Err(err) => error!("Unable to checkpoint the join state: {}", err),
     }
+    ExitCode::SUCCESS
 }
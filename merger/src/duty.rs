@@ -0,0 +1,41 @@
+//! Duty status sequence of the drivers.
+//!
+//! The registrations that cannot follow the previous ones of their shift, such as an `end_break`
+//! without `start_break` or a break still open at the `end_day`, are left out of its report.
+//! Those that never join a shift, such as an `end_day` before any `start_day`, are dropped once
+//! they fall behind the watermark minus the allowed lateness. Both are published on the anomalies
+//! topic with the status of the driver when they happened.
+
+use crate::models::anomaly::{Anomaly, DutyStatus};
+use crate::models::time_registration::{TimeRegistration, TimeRegistrationType};
+
+/// Status of a driver after a time registration, or why the registration cannot follow `status`.
+///
+/// A driver goes from off duty to on shift with a `start_day`, takes any number of breaks
+/// between a `start_break` and an `end_break`, and goes back off duty with an `end_day`.
+pub fn transition(status: DutyStatus, kind: TimeRegistrationType) -> Result<DutyStatus, &'static str> {
+    use DutyStatus::*;
+    use TimeRegistrationType::*;
+    match (status, kind) {
+        (OffDuty, StartDay) => Ok(OnShift),
+        (OnShift, StartBreak) => Ok(OnBreak),
+        (OnBreak, EndBreak) => Ok(OnShift),
+        (OnShift, EndDay) => Ok(OffDuty),
+        (OffDuty, StartBreak) => Err("start_break while off duty"),
+        (OffDuty, EndBreak) => Err("end_break while off duty"),
+        (OffDuty, EndDay) => Err("end_day without start_day"),
+        (OnShift, StartDay) => Err("start_day while on shift"),
+        (OnShift, EndBreak) => Err("end_break without start_break"),
+        (OnBreak, StartDay) => Err("start_day while on break"),
+        (OnBreak, StartBreak) => Err("start_break while on break"),
+        (OnBreak, EndDay) => Err("end_day while on break"),
+    }
+}
+
+pub fn anomaly(registration: &TimeRegistration, status: DutyStatus, reason: &str) -> Anomaly {
+    Anomaly {
+        registration: registration.clone(),
+        status,
+        reason: reason.to_string(),
+    }
+}
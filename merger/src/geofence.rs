@@ -0,0 +1,156 @@
+//! Geofences of the depots and customer sites, read from the GeoJSON feature collection of the
+//! `geofences` setting.
+//!
+//! A geofence is a `Polygon` or a `MultiPolygon`, or a `Point` with a `radius_m` property for a
+//! circle, identified by its `id` property and named by its `name` one. The geofences are indexed
+//! by their bounding box in an R-tree, so that a position is only checked against those around
+//! it. A truck entering or leaving a geofence between two of its positions, taken in event time
+//! order, is published on the geofence topic, the exit giving how long it stayed inside.
+
+use crate::metrics::distance_km;
+use crate::models::position::Position;
+use geo::{BoundingRect, Contains, Geometry, MultiPolygon, Point};
+use geojson::{feature::Id, Feature, GeoJson};
+use rstar::primitives::{GeomWithData, Rectangle};
+use rstar::RTree;
+use std::error::Error;
+use std::fmt::{self, Display};
+use std::path::Path;
+
+/// Kilometres per degree of latitude
+const KM_PER_DEGREE: f64 = 111.32;
+
+#[derive(Debug)]
+pub struct GeofenceError(pub String);
+
+impl Display for GeofenceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl Error for GeofenceError {}
+
+/// Area covered by a geofence, in (longitude, latitude) coordinates
+#[derive(Debug, Clone)]
+pub enum Area {
+    Polygon(MultiPolygon<f64>),
+    Circle { center: Point<f64>, radius_km: f64 },
+}
+
+/// A depot or a customer site
+#[derive(Debug, Clone)]
+pub struct Geofence {
+    pub id: String,
+    pub name: String,
+    pub area: Area,
+}
+
+impl Geofence {
+    pub fn contains(&self, latitude: f64, longitude: f64) -> bool {
+        match &self.area {
+            Area::Polygon(polygon) => polygon.contains(&Point::new(longitude, latitude)),
+            Area::Circle { center, radius_km } => distance_km((center.y(), center.x()), (latitude, longitude)) <= *radius_km,
+        }
+    }
+
+    /// Bounding box of the area, as its (longitude, latitude) corners
+    fn envelope(&self) -> Option<([f64; 2], [f64; 2])> {
+        match &self.area {
+            Area::Polygon(polygon) => {
+                let rect = polygon.bounding_rect()?;
+                Some((rect.min().into(), rect.max().into()))
+            }
+            Area::Circle { center, radius_km } => {
+                let d_lat = radius_km / KM_PER_DEGREE;
+                let d_lon = radius_km / (KM_PER_DEGREE * center.y().to_radians().cos().max(f64::EPSILON));
+                Some(([center.x() - d_lon, center.y() - d_lat], [center.x() + d_lon, center.y() + d_lat]))
+            }
+        }
+    }
+
+    /// Reads a feature with an `id` and a `name`, either a polygon or a point with a `radius_m`
+    fn from_feature(feature: Feature) -> Result<Self, GeofenceError> {
+        let id = match (feature.property("id").and_then(|id| id.as_str()), &feature.id) {
+            (Some(id), _) => id.to_string(),
+            (None, Some(Id::String(id))) => id.clone(),
+            (None, Some(Id::Number(id))) => id.to_string(),
+            (None, None) => return Err(GeofenceError("geofence without id".to_string())),
+        };
+        let name = feature.property("name").and_then(|name| name.as_str()).unwrap_or(&id).to_string();
+        let radius_m = feature.property("radius_m").and_then(|radius| radius.as_f64());
+        let geometry = feature
+            .geometry
+            .ok_or_else(|| GeofenceError(format!("geofence {} without geometry", id)))?;
+        let geometry = Geometry::<f64>::try_from(geometry).map_err(|err| GeofenceError(format!("geofence {}: {}", id, err)))?;
+        let area = match (geometry, radius_m) {
+            (Geometry::Polygon(polygon), _) => Area::Polygon(MultiPolygon(vec![polygon])),
+            (Geometry::MultiPolygon(polygon), _) => Area::Polygon(polygon),
+            (Geometry::Point(center), Some(radius_m)) => Area::Circle { center, radius_km: radius_m / 1000.0 },
+            (Geometry::Point(_), None) => return Err(GeofenceError(format!("circle geofence {} without radius_m", id))),
+            _ => return Err(GeofenceError(format!("geofence {} is neither a polygon nor a circle", id))),
+        };
+        Ok(Geofence { id, name, area })
+    }
+}
+
+/// The geofences, indexed by their bounding box so that a position is only checked against the
+/// areas around it
+#[derive(Debug, Default)]
+pub struct GeofenceRegistry {
+    geofences: Vec<Geofence>,
+    index: RTree<GeomWithData<Rectangle<[f64; 2]>, usize>>,
+}
+
+impl GeofenceRegistry {
+    pub fn new(geofences: Vec<Geofence>) -> Self {
+        let boxes = geofences
+            .iter()
+            .enumerate()
+            .filter_map(|(index, geofence)| {
+                let (min, max) = geofence.envelope()?;
+                Some(GeomWithData::new(Rectangle::from_corners(min, max), index))
+            })
+            .collect();
+        Self {
+            geofences,
+            index: RTree::bulk_load(boxes),
+        }
+    }
+
+    /// Reads the features of a GeoJSON feature collection
+    pub fn from_geojson(geojson: &str) -> Result<Self, GeofenceError> {
+        let geojson: GeoJson = geojson.parse().map_err(|err| GeofenceError(format!("invalid GeoJSON: {}", err)))?;
+        let GeoJson::FeatureCollection(collection) = geojson else {
+            return Err(GeofenceError("the geofences are not a feature collection".to_string()));
+        };
+        let geofences = collection
+            .features
+            .into_iter()
+            .map(Geofence::from_feature)
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self::new(geofences))
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, GeofenceError> {
+        let path = path.as_ref();
+        let geojson = std::fs::read_to_string(path).map_err(|err| GeofenceError(format!("unable to read {}: {}", path.display(), err)))?;
+        Self::from_geojson(&geojson)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.geofences.is_empty()
+    }
+
+    pub fn get(&self, id: &str) -> Option<&Geofence> {
+        self.geofences.iter().find(|geofence| geofence.id == id)
+    }
+
+    /// Geofences a position is inside of
+    pub fn containing<'a>(&'a self, position: &'a Position) -> impl Iterator<Item = &'a Geofence> + 'a {
+        self.index
+            .locate_all_at_point(&[position.longitude, position.latitude])
+            .map(|entry| &self.geofences[entry.data])
+            .filter(|geofence| geofence.contains(position.latitude, position.longitude))
+    }
+}
@@ -0,0 +1,1096 @@
+//! Event-time join of the entities, the time registrations and the positions into the reports.
+//!
+//! Each partition of the time registration and position topics has a watermark, the greatest
+//! event time read from it. Events older than the smallest watermark minus
+//! `join.allowed_lateness_secs` go to the late topic instead of being joined, and the buffered
+//! shifts and positions that can no longer be joined are dropped. A shift is over once this bound
+//! passes its `end_day`, or, in the batch mode which has no watermark, as soon as its `end_day`
+//! is read.
+//!
+//! With `join.position_matching = nearest` a registration takes the position of its truck nearest
+//! in time within `join.position_tolerance_secs`. With `interpolate` its location is interpolated
+//! between the positions before and after it, or taken from the only one within the tolerance.
+//! Each location of a report tells how it was matched: `offset` is the number of seconds from the
+//! registration to the nearest position used, negative when the position is earlier, and
+//! `interpolated` whether the coordinates were interpolated, its timestamp being then the one of
+//! the registration.
+//!
+//! A report is emitted once every break of its shift ended, `breaks` listing them in time order.
+//! A shift without break is reported with an empty `breaks`, a `rest_time` of `0`, the `rest`
+//! coordinates and offset at `0` and an empty `timestamp_rest`. `shift_duration` goes from
+//! `start_time` to `end_time`, `break_duration` sums the breaks and `driving_duration` is the rest
+//! of the shift, in seconds. `distance_km` sums the haversine distances between the consecutive
+//! positions of the truck during the shift, `average_speed_kmh` is this distance over the driving
+//! duration and `max_speed_kmh` the greatest speed between two consecutive positions.
+//!
+//! An erasure request drops the driver, its buffered registrations and shifts, its working days
+//! and its assignments from the state, and keeps the driver as erased: its later entity records
+//! are ignored and its events rejected, so no report carries its personal data anymore.
+
+use crate::assignment::{self, Assignment, MOVING_SPEED_KMH};
+use crate::compliance::{self, WorkDay};
+use crate::config::{JoinConfig, PositionMatching};
+use crate::duty;
+use crate::geofence::GeofenceRegistry;
+use crate::metrics::{distance_km, ShiftMetrics};
+use crate::models::alert::{Alert, AlertType};
+use crate::models::anomaly::{Anomaly, DutyStatus};
+use crate::models::compliance::Violation;
+use crate::models::entity::{Driver, Entity, Truck};
+use crate::models::erasure::ErasureRequest;
+use crate::models::geofence::{GeofenceEvent, GeofenceEventType};
+use crate::models::position::Position;
+use crate::models::report::{Break, Report};
+use crate::models::time_registration::{TimeRegistration, TimeRegistrationType};
+use crate::state::{InMemoryStore, StateStore, Table};
+use chrono::{DateTime, Duration, Utc};
+use kafkamion_broker::{BrokerError, Source};
+use log::{debug, error, info, warn};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt::{self, Display};
+
+/// Allowed lateness of the events when none is configured
+pub const DEFAULT_ALLOWED_LATENESS: Duration = Duration::hours(1);
+
+/// Time between a time registration and the position locating it when no tolerance is configured
+pub const DEFAULT_POSITION_TOLERANCE: Duration = Duration::minutes(2);
+
+/// Minimum progress of the watermark, in event time, between two evictions of the expired state
+const EVICTION_STEP: Duration = Duration::minutes(1);
+
+/// The time registrations of one driver's day, from `start_day` to `end_day`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct Shift {
+    driver_id: String,
+    truck_id: String,
+    start_day: Option<String>,
+    /// Start and end of each break, in event time order
+    #[serde(default)]
+    breaks: Vec<(String, Option<String>)>,
+    end_day: Option<String>,
+}
+
+impl Shift {
+    /// Builds a shift from its registrations sorted by event time, replaying the duty status of
+    /// its driver. The registrations that cannot follow the previous ones are left out of the
+    /// shift and returned as anomalies, as is a `start_break` still open at the `end_day`.
+    fn from_registrations<'a>(registrations: impl IntoIterator<Item = &'a TimeRegistration>) -> (Self, Vec<Anomaly>) {
+        let mut shift = Shift::default();
+        let mut anomalies = Vec::new();
+        let mut status = DutyStatus::OffDuty;
+        let mut open_break = None;
+        for registration in registrations {
+            if status == DutyStatus::OnBreak && registration.kind == TimeRegistrationType::EndDay {
+                if let Some(start_break) = open_break.take() {
+                    anomalies.push(duty::anomaly(start_break, DutyStatus::OnShift, "start_break without end_break"));
+                }
+                shift.breaks.pop();
+                status = DutyStatus::OnShift;
+            }
+            status = match duty::transition(status, registration.kind) {
+                Ok(next) => next,
+                Err(reason) => {
+                    anomalies.push(duty::anomaly(registration, status, reason));
+                    continue;
+                }
+            };
+            let timestamp = Some(registration.timestamp.clone());
+            match registration.kind {
+                TimeRegistrationType::StartDay => {
+                    shift.driver_id = registration.driver_id.clone();
+                    shift.truck_id = registration.truck_id.clone();
+                    shift.start_day = timestamp;
+                }
+                TimeRegistrationType::StartBreak => {
+                    shift.breaks.push((registration.timestamp.clone(), None));
+                    open_break = Some(registration);
+                }
+                TimeRegistrationType::EndBreak => {
+                    if let Some((_, end)) = shift.breaks.last_mut() {
+                        *end = timestamp;
+                    }
+                    open_break = None;
+                }
+                TimeRegistrationType::EndDay => shift.end_day = timestamp,
+            }
+        }
+        (shift, anomalies)
+    }
+}
+
+/// A position with its event time
+type TimedPosition = (i64, Position);
+
+/// Where a truck was at the time of a registration
+#[derive(Default)]
+struct Location {
+    latitude: f64,
+    longitude: f64,
+    /// Timestamp of the nearest position, or of the registration when interpolated
+    timestamp: String,
+    /// Seconds from the registration to the nearest position
+    offset: i64,
+    interpolated: bool,
+}
+
+/// Version of a stored driver or truck
+#[derive(Deserialize)]
+struct Versioned {
+    #[serde(default)]
+    version: u64,
+}
+
+/// Geofences a truck is inside of, as of its latest position
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct Presence {
+    /// Event time of the latest position of the truck
+    time: i64,
+    /// Event time the truck entered each geofence it is inside of, by geofence id
+    inside: BTreeMap<String, i64>,
+}
+
+/// Latest position of a truck checked for a driver on shift
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Attendance {
+    /// Event time of the position
+    time: i64,
+    latitude: f64,
+    longitude: f64,
+    /// Whether the truck moved with no driver on shift up to this position
+    unattended: bool,
+}
+
+/// A record the join publishes besides the reports, each kind on its own topic
+#[derive(Debug, Clone, PartialEq)]
+pub enum Output {
+    /// Breach of a driving hours rule by a finished shift
+    Violation(Violation),
+    /// Time registration out of sequence, left out of the shifts
+    Anomaly(Anomaly),
+    /// Truck entering or leaving a geofence
+    Geofence(GeofenceEvent),
+    /// Conflict between the assignments of the drivers and the trucks
+    Alert(Alert),
+}
+
+/// Why an event was not joined
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Rejection {
+    /// The event is older than the watermark minus the allowed lateness, its window is closed
+    Late { event_time: i64, watermark: i64 },
+    InvalidTimestamp(String),
+    /// The event references a driver that is not in the entity table yet
+    UnknownDriver(String),
+    /// The event references a truck that is not in the entity table yet
+    UnknownTruck(String),
+    /// The event references a driver erased under the right to erasure
+    ErasedDriver(String),
+}
+
+impl Rejection {
+    /// Whether the event may be joined later, once the missing entity is known
+    pub fn is_transient(&self) -> bool {
+        matches!(self, Rejection::UnknownDriver(_) | Rejection::UnknownTruck(_))
+    }
+}
+
+impl Display for Rejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let date = |micros: i64| DateTime::from_timestamp_micros(micros).map(|date| date.to_rfc3339()).unwrap_or_default();
+        match self {
+            Rejection::Late { event_time, watermark } => {
+                write!(f, "event of {} behind the watermark {} by more than the allowed lateness", date(*event_time), date(*watermark))
+            }
+            Rejection::InvalidTimestamp(timestamp) => write!(f, "invalid timestamp {:?}", timestamp),
+            Rejection::UnknownDriver(driver_id) => write!(f, "unknown driver {}", driver_id),
+            Rejection::UnknownTruck(truck_id) => write!(f, "unknown truck {}", truck_id),
+            Rejection::ErasedDriver(driver_id) => write!(f, "driver {} erased", driver_id),
+        }
+    }
+}
+
+/// Stateful join of the three input topics, in event time.
+///
+/// Entities are kept in a table keyed by `driver_id` / `truck_id`, the events referencing an
+/// unknown one being rejected so that they can be retried once it is known. Only the latest
+/// version of an entity is kept, and a tombstone deletes it, the reports built afterwards
+/// reflecting the change. An erasure request drops a driver and everything buffered about it
+/// for good, its later records being refused. Time registrations are buffered per driver until
+/// they form a shift from a `start_day` to an `end_day`, whatever order they arrive in, and the
+/// watermark minus the allowed lateness passed its `end_day`, as a registration of the shift
+/// may still arrive until then. Positions are indexed per truck by event time. A `Report` is
+/// emitted as soon as a finished shift has its driver, its truck and a location for each of its
+/// start, break and end registrations, with the durations and the distance derived from the
+/// positions of its truck.
+///
+/// A registration is located by the position of its truck nearest in time within the position
+/// tolerance, or interpolated between the positions around it. Unless a position has the exact
+/// time of the registration, the location waits for a later position of the truck, or for the
+/// watermark to pass the tolerance, as a nearer position may still arrive. `flush` assembles
+/// the remaining shifts and locates them with the positions received so far.
+///
+/// The registrations of a driver are replayed through its duty status, off duty, on shift or on
+/// break, when its shifts are assembled. Those that cannot follow the previous ones are left out
+/// of the shift and kept as anomalies, as are the registrations expiring outside of any shift.
+///
+/// Every finished shift is also checked against the driving hours rules, with the working days
+/// of its driver from the last weeks. Each position is checked against the geofences, the
+/// trucks entering and leaving them being followed in event time.
+///
+/// The `start_day` and `end_day` registrations assign their driver to their truck. A truck
+/// assigned to two drivers at once, a driver assigned to two trucks at once and a truck starting
+/// to move with no driver on shift, within the position tolerance, are alerted. The violations,
+/// the anomalies, the geofence events and the alerts are kept until `take_outputs`.
+///
+/// Each tracked input partition has a watermark, the greatest event time it delivered. Once
+/// every tracked partition delivered an event, the events older than the smallest watermark
+/// minus the allowed lateness are rejected as late, and the shifts, registrations and positions
+/// that can no longer be joined are evicted. Nothing is late while no partition is tracked, a
+/// shift being assembled as soon as its `end_day` arrives.
+///
+/// The tables live in a `StateStore`, in memory unless another store is given.
+pub struct Joiner {
+    store: Box<dyn StateStore>,
+    allowed_lateness: Duration,
+    position_tolerance: Duration,
+    position_matching: PositionMatching,
+    /// Input partitions holding back the watermark
+    tracked: HashSet<String>,
+    /// Event time up to which the state was last evicted
+    evicted_until: Option<i64>,
+    /// Records to publish besides the reports, since they were last taken
+    outputs: Vec<Output>,
+    geofences: GeofenceRegistry,
+}
+
+impl Default for Joiner {
+    fn default() -> Self {
+        Self::with_store(Box::new(InMemoryStore::new()))
+    }
+}
+
+impl Joiner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_store(store: Box<dyn StateStore>) -> Self {
+        Self {
+            store,
+            allowed_lateness: DEFAULT_ALLOWED_LATENESS,
+            position_tolerance: DEFAULT_POSITION_TOLERANCE,
+            position_matching: PositionMatching::Nearest,
+            tracked: HashSet::new(),
+            evicted_until: None,
+            outputs: Vec::new(),
+            geofences: GeofenceRegistry::default(),
+        }
+    }
+
+    pub fn with_allowed_lateness(mut self, allowed_lateness: Duration) -> Self {
+        self.allowed_lateness = allowed_lateness;
+        self
+    }
+
+    pub fn with_position_matching(mut self, matching: PositionMatching, tolerance: Duration) -> Self {
+        self.position_matching = matching;
+        self.position_tolerance = tolerance;
+        self
+    }
+
+    /// Applies the event time settings of the configuration, the same in streaming and in batch
+    pub fn with_config(self, config: &JoinConfig) -> Self {
+        self.with_allowed_lateness(Duration::seconds(config.allowed_lateness_secs))
+            .with_position_matching(config.position_matching, Duration::seconds(config.position_tolerance_secs))
+    }
+
+    pub fn with_geofences(mut self, geofences: GeofenceRegistry) -> Self {
+        self.geofences = geofences;
+        self
+    }
+
+    /// Forgets the whole state, before it is rebuilt from the changelog
+    pub fn clear(&mut self) {
+        self.store.clear();
+    }
+
+    /// The first change of the state that could not be logged since the last call, see
+    /// `StateStore::take_failure`
+    pub fn take_failure(&mut self) -> Option<BrokerError> {
+        self.store.take_failure()
+    }
+
+    /// Writes the state not yet on disk, before it is checkpointed
+    pub fn persist(&mut self) {
+        self.store.persist();
+    }
+
+    /// Rebuilds the state from the records of a changelog, see `ChangelogStore`
+    pub fn restore(&mut self, source: &mut dyn Source, poll_timeout: std::time::Duration) -> usize {
+        self.store.restore(source, poll_timeout)
+    }
+
+    /// Makes an input partition hold back the watermark until it delivers events
+    pub fn track(&mut self, partition: &str) {
+        self.tracked.insert(partition.to_string());
+    }
+
+    pub fn untrack(&mut self, partition: &str) {
+        self.tracked.remove(partition);
+    }
+
+    /// Smallest watermark of the tracked partitions, `None` until each of them delivered an event
+    pub fn watermark(&self) -> Option<i64> {
+        if self.tracked.is_empty() {
+            return None;
+        }
+        self.tracked
+            .iter()
+            .map(|partition| self.load::<i64>(Table::Watermarks, partition))
+            .collect::<Option<Vec<i64>>>()?
+            .into_iter()
+            .min()
+    }
+
+    fn load<T: DeserializeOwned>(&self, table: Table, key: &str) -> Option<T> {
+        decode(table, key, &self.store.get(table, key)?)
+    }
+
+    fn save<T: Serialize>(&mut self, table: Table, key: &str, value: &T) {
+        let value = serde_json::to_vec(value).expect("State serialization failed");
+        self.store.put(table, key, value);
+    }
+
+    /// Keeps a driver or a truck, unless a later version of it is already known
+    pub fn on_entity(&mut self, entity: Entity) -> Vec<Report> {
+        let table = match entity {
+            Entity::Driver(_) => Table::Drivers,
+            Entity::Truck(_) => Table::Trucks,
+        };
+        if table == Table::Drivers && self.store.get(Table::Erasures, entity.id()).is_some() {
+            info!("Skipping driver {}, erased", entity.id());
+            return Vec::new();
+        }
+        if let Some(known) = self.load::<Versioned>(table, entity.id()).filter(|known| known.version > entity.version()) {
+            debug!("Skipping version {} of {} {}, version {} is known", entity.version(), table.name(), entity.id(), known.version);
+            return Vec::new();
+        }
+        match entity {
+            Entity::Driver(driver) => self.save(Table::Drivers, &driver.driver_id, &driver),
+            Entity::Truck(truck) => self.save(Table::Trucks, &truck.truck_id, &truck),
+        }
+        self.drain_ready(false)
+    }
+
+    /// Deletes the driver or the truck keyed by a tombstone of the entity topic. The events
+    /// referencing it are rejected from then on, and its finished shifts still waiting to be
+    /// joined expire.
+    pub fn on_entity_deleted(&mut self, id: &str) {
+        for table in [Table::Drivers, Table::Trucks] {
+            if self.store.get(table, id).is_some() {
+                info!("Deleting {} {}", table.name(), id);
+                self.store.delete(table, id);
+            }
+        }
+    }
+
+    /// Erases a driver under the right to erasure: its entity, registrations, shifts, working
+    /// days and assignments are dropped, and it is kept as erased so that no later record of it
+    /// is stored or joined into a report
+    pub fn on_erasure(&mut self, request: ErasureRequest) {
+        let driver_id = request.driver_id.as_str();
+        self.store.delete(Table::Drivers, driver_id);
+        let prefix = format!("{}/", driver_id);
+        for table in [Table::Registrations, Table::ClosedShifts, Table::WorkDays] {
+            for (key, _) in self.store.scan(table, &prefix) {
+                self.store.delete(table, &key);
+            }
+        }
+        self.store.delete(Table::Assignments, &format!("driver/{}", driver_id));
+        for (key, value) in self.store.scan(Table::Assignments, "truck/") {
+            let Some(mut assignments) = decode::<Vec<Assignment>>(Table::Assignments, &key, &value) else {
+                continue;
+            };
+            let count = assignments.len();
+            assignments.retain(|assignment| assignment.driver_id != driver_id);
+            if assignments.len() < count {
+                self.save_assignments(&key, &assignments);
+            }
+        }
+        info!("Erased driver {} as requested at {}", driver_id, request.requested_at);
+        self.save(Table::Erasures, driver_id, &request);
+    }
+
+    /// Assembles the shifts still waiting for late registrations and builds the reports of the
+    /// finished shifts that can be located with the positions received so far, once no more
+    /// event is expected
+    pub fn flush(&mut self) -> Vec<Report> {
+        for driver_id in self.buffered_drivers() {
+            self.assemble(&driver_id, true);
+        }
+        for truck_id in self.positioned_trucks() {
+            self.check_attended(&truck_id, i64::MAX);
+        }
+        self.drain_ready(true)
+    }
+
+    /// Joins a time registration read from `partition`
+    pub fn on_time_registration(&mut self, partition: &str, registration: TimeRegistration) -> Result<Vec<Report>, Rejection> {
+        self.check_driver(&registration.driver_id)?;
+        self.check_truck(&registration.truck_id)?;
+        let time = self.advance(partition, &registration.timestamp)?;
+        let key = format!("{}/{}/{}", registration.driver_id, time_key(time), registration.kind);
+        self.save(Table::Registrations, &key, &registration);
+        self.assign(&registration, time);
+        self.assemble(&registration.driver_id, false);
+        let mut reports = self.drain_ready(false);
+        reports.extend(self.evict_expired());
+        Ok(reports)
+    }
+
+    /// Joins a position read from `partition`
+    pub fn on_position(&mut self, partition: &str, position: Position) -> Result<Vec<Report>, Rejection> {
+        self.check_truck(&position.truck_id)?;
+        let time = self.advance(partition, &position.timestamp)?;
+        let key = format!("{}/{}", position.truck_id, time_key(time));
+        self.save(Table::Positions, &key, &position);
+        self.track_geofences(&position, time);
+        // a registration assigning a driver to the truck may arrive within the allowed lateness
+        if self.is_final(time.saturating_add(self.tolerance_micros())) {
+            self.check_attended(&position.truck_id, time);
+        }
+        let mut reports = self.drain_ready(false);
+        reports.extend(self.evict_expired());
+        Ok(reports)
+    }
+
+    /// Compares the geofences a truck is inside of with those of its previous position, emitting
+    /// an event for each geofence it entered or left. A position older than the latest one of
+    /// its truck is skipped, as it would reorder the events already emitted.
+    fn track_geofences(&mut self, position: &Position, time: i64) {
+        if self.geofences.is_empty() {
+            return;
+        }
+        let mut presence: Presence = self.load(Table::Geofences, &position.truck_id).unwrap_or_default();
+        if time <= presence.time {
+            debug!("Position of truck {} at {} older than its latest one, not checked against the geofences", position.truck_id, position.timestamp);
+            return;
+        }
+        let inside: Vec<(String, String)> = self
+            .geofences
+            .containing(position)
+            .map(|geofence| (geofence.id.clone(), geofence.name.clone()))
+            .collect();
+
+        let mut events = Vec::new();
+        for (id, entered) in &presence.inside {
+            if !inside.iter().any(|(inside, _)| inside == id) {
+                let name = self.geofences.get(id).map_or(id.as_str(), |geofence| geofence.name.as_str());
+                let dwell = (time - entered) / 1_000_000;
+                events.push(geofence_event(GeofenceEventType::Exit, position, id, name, Some(dwell)));
+            }
+        }
+        presence.inside.retain(|id, _| inside.iter().any(|(inside, _)| inside == id));
+        for (id, name) in &inside {
+            if !presence.inside.contains_key(id) {
+                presence.inside.insert(id.clone(), time);
+                events.push(geofence_event(GeofenceEventType::Enter, position, id, name, None));
+            }
+        }
+        presence.time = time;
+        self.save(Table::Geofences, &position.truck_id, &presence);
+        self.outputs.extend(events.into_iter().map(Output::Geofence));
+    }
+
+    /// Records the assignment of a `start_day` or an `end_day` for its truck and for its driver.
+    /// Once started, the assignment is checked against those of the other drivers of the truck
+    /// and of the other trucks of the driver.
+    fn assign(&mut self, registration: &TimeRegistration, time: i64) {
+        let truck_key = format!("truck/{}", registration.truck_id);
+        let driver_key = format!("driver/{}", registration.driver_id);
+        let mut by_truck: Vec<Assignment> = self.load(Table::Assignments, &truck_key).unwrap_or_default();
+        let mut by_driver: Vec<Assignment> = self.load(Table::Assignments, &driver_key).unwrap_or_default();
+        let Some(current) = assignment::record(&mut by_truck, registration, time) else {
+            return;
+        };
+        assignment::record(&mut by_driver, registration, time);
+        self.save(Table::Assignments, &truck_key, &by_truck);
+        self.save(Table::Assignments, &driver_key, &by_driver);
+        if registration.kind != TimeRegistrationType::StartDay {
+            return;
+        }
+
+        let mut drivers: Vec<String> = by_truck
+            .iter()
+            .filter(|other| other.driver_id != current.driver_id && other.overlaps(&current))
+            .map(|other| other.driver_id.clone())
+            .collect();
+        if !drivers.is_empty() {
+            warn!("Truck {} assigned to drivers {:?} and {} at {}", current.truck_id, drivers, current.driver_id, registration.timestamp);
+            drivers.push(current.driver_id.clone());
+            self.alert(AlertType::DoubleBookedTruck, vec![current.truck_id.clone()], drivers, &registration.timestamp);
+        }
+        let mut trucks: Vec<String> = by_driver
+            .iter()
+            .filter(|other| other.truck_id != current.truck_id && other.overlaps(&current))
+            .map(|other| other.truck_id.clone())
+            .collect();
+        if !trucks.is_empty() {
+            warn!("Driver {} assigned to trucks {:?} and {} at {}", current.driver_id, trucks, current.truck_id, registration.timestamp);
+            trucks.push(current.truck_id.clone());
+            self.alert(AlertType::DriverOnTwoTrucks, trucks, vec![current.driver_id.clone()], &registration.timestamp);
+        }
+    }
+
+    /// Checks the positions of a truck up to `until` in event time order, alerting on the truck
+    /// starting to move with no driver on shift, once per move: a position following one that
+    /// already moved unattended is not alerted again. A position older than the latest one
+    /// checked is skipped.
+    fn check_attended(&mut self, truck_id: &str, until: i64) {
+        let mut latest: Option<Attendance> = self.load(Table::Attendance, truck_id);
+        let from = latest.as_ref().map_or(i64::MIN, |latest| latest.time.saturating_add(1));
+        let positions = self.positions_between(truck_id, from, until);
+        if positions.is_empty() {
+            return;
+        }
+        let assignments: Vec<Assignment> = self.load(Table::Assignments, &format!("truck/{}", truck_id)).unwrap_or_default();
+        let tolerance = self.tolerance_micros();
+        for (time, position) in positions {
+            let moving = latest.as_ref().is_some_and(|previous| {
+                let hours = (time - previous.time) as f64 / 3_600_000_000.0;
+                let distance = distance_km((previous.latitude, previous.longitude), (position.latitude, position.longitude));
+                distance / hours > MOVING_SPEED_KMH
+            });
+            let unattended = moving && !assignments.iter().any(|assignment| assignment.covers(time, tolerance));
+            if unattended && !latest.as_ref().is_some_and(|previous| previous.unattended) {
+                warn!("Truck {} moving with no driver on shift at {}", truck_id, position.timestamp);
+                self.alert(AlertType::TruckMovingWithoutDriver, vec![truck_id.to_string()], Vec::new(), &position.timestamp);
+            }
+            latest = Some(Attendance {
+                time,
+                latitude: position.latitude,
+                longitude: position.longitude,
+                unattended,
+            });
+        }
+        self.save(Table::Attendance, truck_id, &latest);
+    }
+
+    /// Saves the assignments of a truck or of a driver, deleting the entry once none is left
+    fn save_assignments(&mut self, key: &str, assignments: &[Assignment]) {
+        if assignments.is_empty() {
+            self.store.delete(Table::Assignments, key);
+        } else {
+            self.save(Table::Assignments, key, &assignments);
+        }
+    }
+
+    fn alert(&mut self, kind: AlertType, truck_ids: Vec<String>, driver_ids: Vec<String>, timestamp: &str) {
+        self.outputs.push(Output::Alert(Alert {
+            kind,
+            truck_ids,
+            driver_ids,
+            timestamp: timestamp.to_string(),
+        }));
+    }
+
+    fn check_driver(&self, driver_id: &str) -> Result<(), Rejection> {
+        if self.store.get(Table::Erasures, driver_id).is_some() {
+            return Err(Rejection::ErasedDriver(driver_id.to_string()));
+        }
+        match self.store.get(Table::Drivers, driver_id) {
+            Some(_) => Ok(()),
+            None => Err(Rejection::UnknownDriver(driver_id.to_string())),
+        }
+    }
+
+    fn check_truck(&self, truck_id: &str) -> Result<(), Rejection> {
+        match self.store.get(Table::Trucks, truck_id) {
+            Some(_) => Ok(()),
+            None => Err(Rejection::UnknownTruck(truck_id.to_string())),
+        }
+    }
+
+    /// Rejects the event if it is late, otherwise moves the watermark of its partition forward,
+    /// the state it expires being evicted once the event is joined. Returns the event time.
+    fn advance(&mut self, partition: &str, timestamp: &str) -> Result<i64, Rejection> {
+        let time = event_time(timestamp).ok_or_else(|| Rejection::InvalidTimestamp(timestamp.to_string()))?;
+        if let Some(watermark) = self.watermark() {
+            if time < watermark - self.lateness_micros() {
+                return Err(Rejection::Late { event_time: time, watermark });
+            }
+        }
+        let current: Option<i64> = self.load(Table::Watermarks, partition);
+        if current.is_none_or(|current| time > current) {
+            self.save(Table::Watermarks, partition, &time);
+        }
+        Ok(time)
+    }
+
+    fn lateness_micros(&self) -> i64 {
+        self.allowed_lateness.num_microseconds().unwrap_or(i64::MAX)
+    }
+
+    fn tolerance_micros(&self) -> i64 {
+        self.position_tolerance.num_microseconds().unwrap_or(i64::MAX)
+    }
+
+    /// Whether no registration can be added anymore to a shift ending at `end`: the watermark
+    /// minus the allowed lateness passed it, or no partition is tracked and nothing is late
+    fn is_final(&self, end: i64) -> bool {
+        if self.tracked.is_empty() {
+            return true;
+        }
+        self.watermark().is_some_and(|watermark| watermark - self.lateness_micros() > end)
+    }
+
+    /// Drivers with buffered registrations
+    fn buffered_drivers(&self) -> Vec<String> {
+        let mut drivers: Vec<String> = self
+            .store
+            .scan(Table::Registrations, "")
+            .into_iter()
+            .filter_map(|(key, _)| Some(key.split_once('/')?.0.to_string()))
+            .collect();
+        drivers.dedup();
+        drivers
+    }
+
+    /// Trucks with stored positions
+    fn positioned_trucks(&self) -> Vec<String> {
+        let mut trucks: Vec<String> = self
+            .store
+            .scan(Table::Positions, "")
+            .into_iter()
+            .filter_map(|(key, _)| Some(key.rsplit_once('/')?.0.to_string()))
+            .collect();
+        trucks.dedup();
+        trucks
+    }
+
+    /// Groups the buffered registrations of a driver into shifts. In event time order, a shift
+    /// goes from a `start_day` to the next `end_day` with no other `start_day` in between. A
+    /// shift is only assembled once final, a registration arriving late within the allowed
+    /// lateness still joining it, unless `flush`.
+    fn assemble(&mut self, driver_id: &str, flush: bool) {
+        let registrations: Vec<(String, TimeRegistration)> = self
+            .store
+            .scan(Table::Registrations, &format!("{}/", driver_id))
+            .into_iter()
+            .filter_map(|(key, value)| Some((key.clone(), decode(Table::Registrations, &key, &value)?)))
+            .collect();
+
+        let mut start = None;
+        for (index, (_, registration)) in registrations.iter().enumerate() {
+            match registration.kind {
+                TimeRegistrationType::StartDay => start = Some(index),
+                TimeRegistrationType::EndDay => {
+                    let Some(first) = start.take() else {
+                        continue;
+                    };
+                    let end = event_time(&registration.timestamp).unwrap_or_default();
+                    if !flush && !self.is_final(end) {
+                        break;
+                    }
+                    let entries = &registrations[first..=index];
+                    let (shift, anomalies) = Shift::from_registrations(entries.iter().map(|(_, registration)| registration));
+                    self.outputs.extend(anomalies.into_iter().map(Output::Anomaly));
+                    for (key, _) in entries {
+                        self.store.delete(Table::Registrations, key);
+                    }
+                    let start_time = event_time(&entries[0].1.timestamp).unwrap_or_default();
+                    self.save(Table::ClosedShifts, &format!("{}/{}", driver_id, time_key(start_time)), &shift);
+                    self.check_compliance(&shift);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Checks a finished shift against the driving hours rules and keeps it as a working day of
+    /// its driver for the rules on the next ones
+    fn check_compliance(&mut self, shift: &Shift) {
+        let (Some(start), Some(end)) = (
+            shift.start_day.as_deref().and_then(event_time),
+            shift.end_day.as_deref().and_then(event_time),
+        ) else {
+            return;
+        };
+        let breaks = shift
+            .breaks
+            .iter()
+            .filter_map(|(start, end)| Some((event_time(start)?, event_time(end.as_deref()?)?)))
+            .collect();
+        let day = WorkDay { start, end, breaks };
+        let history: Vec<WorkDay> = self
+            .store
+            .scan(Table::WorkDays, &format!("{}/", shift.driver_id))
+            .into_iter()
+            .filter_map(|(key, value)| decode(Table::WorkDays, &key, &value))
+            .collect();
+        let violations = compliance::evaluate(&shift.driver_id, &day, &history);
+        self.outputs.extend(violations.into_iter().map(Output::Violation));
+        self.save(Table::WorkDays, &format!("{}/{}", shift.driver_id, time_key(start)), &day);
+    }
+
+    /// Violations and anomalies found since the last call
+    pub fn take_outputs(&mut self) -> Vec<Output> {
+        std::mem::take(&mut self.outputs)
+    }
+
+    /// Assembles the shifts the watermark made final, then forgets what can no longer be joined
+    /// once the watermark minus the allowed lateness passed it: the finished shifts still
+    /// missing a position, the registrations that are not part of a shift in progress or waiting
+    /// to be assembled, kept as anomalies, the assignments that ended and the positions that no
+    /// buffered shift can need. The positions are checked for a driver on shift before, once
+    /// the watermark minus the allowed lateness passed them by the position tolerance. Returns
+    /// the reports of the shifts assembled.
+    fn evict_expired(&mut self) -> Vec<Report> {
+        let Some(watermark) = self.watermark() else {
+            return Vec::new();
+        };
+        let cutoff = watermark - self.lateness_micros();
+        let step = EVICTION_STEP.num_microseconds().unwrap_or_default();
+        if self.evicted_until.is_some_and(|until| cutoff < until + step) {
+            return Vec::new();
+        }
+        self.evicted_until = Some(cutoff);
+
+        for driver_id in self.buffered_drivers() {
+            self.assemble(&driver_id, false);
+        }
+        // the shifts just assembled are joined before those expired are dropped
+        let reports = self.drain_ready(false);
+
+        // earliest event time each truck may still need a position at
+        let mut needed_from: HashMap<String, i64> = HashMap::new();
+        let mut need = |truck_id: &str, time: i64| {
+            let from = needed_from.entry(truck_id.to_string()).or_insert(time);
+            *from = (*from).min(time);
+        };
+
+        let tolerance = self.tolerance_micros();
+        for (key, value) in self.store.scan(Table::ClosedShifts, "") {
+            let Some(shift) = decode::<Shift>(Table::ClosedShifts, &key, &value) else {
+                continue;
+            };
+            let end = shift.end_day.as_deref().and_then(event_time).unwrap_or_default();
+            if end.saturating_add(tolerance) < cutoff {
+                warn!("Shift of driver {} from {:?} expired before it could be joined", shift.driver_id, shift.start_day);
+                self.store.delete(Table::ClosedShifts, &key);
+            } else {
+                let start = shift.start_day.as_deref().and_then(event_time).unwrap_or_default();
+                need(&shift.truck_id, start.saturating_sub(tolerance));
+            }
+        }
+
+        let registrations: Vec<(String, TimeRegistration, i64)> = self
+            .store
+            .scan(Table::Registrations, "")
+            .into_iter()
+            .filter_map(|(key, value)| {
+                let registration: TimeRegistration = decode(Table::Registrations, &key, &value)?;
+                let time = event_time(&registration.timestamp)?;
+                Some((key, registration, time))
+            })
+            .collect();
+        // the registrations from a start_day to the next end_day, or to the last one of the
+        // driver, belong to a shift waiting to be assembled or still in progress
+        let mut kept = vec![false; registrations.len()];
+        let mut start: Option<usize> = None;
+        for (index, (_, registration, _)) in registrations.iter().enumerate() {
+            if let Some(first) = start.filter(|first| registrations[*first].1.driver_id != registration.driver_id) {
+                kept[first..index].fill(true);
+                start = None;
+            }
+            match registration.kind {
+                TimeRegistrationType::StartDay => start = Some(index),
+                TimeRegistrationType::EndDay => {
+                    if let Some(first) = start.take() {
+                        kept[first..=index].fill(true);
+                    }
+                }
+                _ => {}
+            }
+        }
+        if let Some(first) = start {
+            kept[first..].fill(true);
+        }
+        // the keys are sorted by driver then event time, the duty status is replayed per driver
+        let mut driver = None;
+        let mut status = DutyStatus::OffDuty;
+        for ((key, registration, time), kept) in registrations.iter().zip(kept) {
+            if driver != Some(registration.driver_id.as_str()) {
+                driver = Some(registration.driver_id.as_str());
+                status = DutyStatus::OffDuty;
+            }
+            // a start_day opens a shift whatever the status, leaving the previous one unfinished
+            let next = match registration.kind {
+                TimeRegistrationType::StartDay => Ok(DutyStatus::OnShift),
+                kind => duty::transition(status, kind),
+            };
+            if *time < cutoff && !kept {
+                let reason = next.err().unwrap_or("shift not ended before the next start_day");
+                warn!("Dropping the {} of driver {} at {}: {}", registration.kind, registration.driver_id, registration.timestamp, reason);
+                self.outputs.push(Output::Anomaly(duty::anomaly(registration, status, reason)));
+                self.store.delete(Table::Registrations, key);
+            } else {
+                need(&registration.truck_id, time.saturating_sub(tolerance));
+            }
+            status = next.unwrap_or(status);
+        }
+
+        for (key, value) in self.store.scan(Table::WorkDays, "") {
+            let Some(day) = decode::<WorkDay>(Table::WorkDays, &key, &value) else {
+                continue;
+            };
+            if day.end < cutoff - compliance::HISTORY {
+                self.store.delete(Table::WorkDays, &key);
+            }
+        }
+
+        // no registration can assign a driver to the positions checked anymore
+        let checked_until = cutoff.saturating_sub(tolerance).saturating_sub(1);
+        for truck_id in self.positioned_trucks() {
+            self.check_attended(&truck_id, checked_until);
+        }
+
+        // an assignment is kept while it may cover a position left to check
+        let expiry = cutoff.saturating_sub(tolerance.saturating_mul(2));
+        for (key, value) in self.store.scan(Table::Assignments, "") {
+            let Some(mut assignments) = decode::<Vec<Assignment>>(Table::Assignments, &key, &value) else {
+                continue;
+            };
+            let count = assignments.len();
+            assignments.retain(|assignment| !assignment.expired(expiry));
+            if assignments.len() < count {
+                self.save_assignments(&key, &assignments);
+            }
+        }
+
+        for (key, _) in self.store.scan(Table::Positions, "") {
+            let Some((truck_id, time)) = key.rsplit_once('/').and_then(|(truck_id, time)| Some((truck_id, time.parse::<i64>().ok()?))) else {
+                continue;
+            };
+            if time <= checked_until && needed_from.get(truck_id).is_none_or(|from| time < *from) {
+                debug!("Evicting the position of truck {} at {}", truck_id, time);
+                self.store.delete(Table::Positions, &key);
+            }
+        }
+        reports
+    }
+
+    /// Builds the reports of every finished shift that can be joined and forgets those shifts.
+    /// With `flush`, no more position is expected to locate them.
+    fn drain_ready(&mut self, flush: bool) -> Vec<Report> {
+        let mut reports = Vec::new();
+        for (key, value) in self.store.scan(Table::ClosedShifts, "") {
+            let Some(shift) = decode::<Shift>(Table::ClosedShifts, &key, &value) else {
+                continue;
+            };
+            if let Some(report) = self.build_report(&shift, flush) {
+                self.store.delete(Table::ClosedShifts, &key);
+                reports.push(report);
+            }
+        }
+        reports
+    }
+
+    /// Locates a truck at the time of a registration. Returns `None` while a nearer position may
+    /// still arrive, unless `flush`, or when no position is within the tolerance.
+    fn locate(&self, truck_id: &str, timestamp: &str, flush: bool) -> Option<Location> {
+        let time = event_time(timestamp)?;
+        let tolerance = self.tolerance_micros();
+        let (before, after) = self.neighbours(truck_id, time);
+        let nearest = |(position_time, position): TimedPosition| Location {
+            latitude: position.latitude,
+            longitude: position.longitude,
+            timestamp: position.timestamp,
+            offset: (position_time - time) / 1_000_000,
+            interpolated: false,
+        };
+        if let Some(exact) = before.clone().filter(|(position_time, _)| *position_time == time) {
+            return Some(nearest(exact));
+        }
+        let settled = flush
+            || after.is_some()
+            || self.watermark().is_some_and(|watermark| watermark - self.lateness_micros() > time.saturating_add(tolerance));
+        if !settled {
+            return None;
+        }
+
+        let before = before.filter(|(position_time, _)| time - position_time <= tolerance);
+        let after = after.filter(|(position_time, _)| position_time - time <= tolerance);
+        match (before, after) {
+            (Some((from_time, from)), Some((to_time, to))) if self.position_matching == PositionMatching::Interpolate => {
+                let fraction = (time - from_time) as f64 / (to_time - from_time) as f64;
+                let offset = if time - from_time <= to_time - time { from_time - time } else { to_time - time };
+                Some(Location {
+                    latitude: from.latitude + (to.latitude - from.latitude) * fraction,
+                    longitude: from.longitude + (to.longitude - from.longitude) * fraction,
+                    timestamp: timestamp.to_string(),
+                    offset: offset / 1_000_000,
+                    interpolated: true,
+                })
+            }
+            (Some(before), Some(after)) => {
+                let nearer = if time - before.0 <= after.0 - time { before } else { after };
+                Some(nearest(nearer))
+            }
+            (before, after) => before.or(after).map(nearest),
+        }
+    }
+
+    /// Latest position of a truck up to `time` and earliest one after it, with their event time
+    fn neighbours(&self, truck_id: &str, time: i64) -> (Option<TimedPosition>, Option<TimedPosition>) {
+        let (mut before, mut after) = (None, None);
+        for (key, value) in self.store.scan(Table::Positions, &format!("{}/", truck_id)) {
+            let Some(position_time) = key.rsplit_once('/').and_then(|(_, time)| time.parse::<i64>().ok()) else {
+                continue;
+            };
+            if position_time <= time {
+                before = Some((position_time, key, value));
+            } else {
+                after = Some((position_time, key, value));
+                break;
+            }
+        }
+        let decode = |(time, key, value): (i64, String, Vec<u8>)| Some((time, decode(Table::Positions, &key, &value)?));
+        (before.and_then(decode), after.and_then(decode))
+    }
+
+    /// Positions of a truck from `from` to `to` included, with their event time, in event time order
+    fn positions_between(&self, truck_id: &str, from: i64, to: i64) -> Vec<(i64, Position)> {
+        self.store
+            .scan(Table::Positions, &format!("{}/", truck_id))
+            .into_iter()
+            .filter_map(|(key, value)| {
+                let time = key.rsplit_once('/')?.1.parse::<i64>().ok()?;
+                if time < from || time > to {
+                    return None;
+                }
+                Some((time, decode(Table::Positions, &key, &value)?))
+            })
+            .collect()
+    }
+
+    fn build_report(&self, shift: &Shift, flush: bool) -> Option<Report> {
+        let driver: Driver = self.load(Table::Drivers, &shift.driver_id)?;
+        let truck: Truck = self.load(Table::Trucks, &shift.truck_id)?;
+        let start_day = shift.start_day.as_ref()?;
+        let end_day = shift.end_day.as_ref()?;
+
+        let start = self.locate(&shift.truck_id, start_day, flush)?;
+        let end = self.locate(&shift.truck_id, end_day, flush)?;
+
+        let mut breaks = Vec::new();
+        for (start_break, end_break) in &shift.breaks {
+            let end_break = end_break.as_ref()?;
+            let location = self.locate(&shift.truck_id, start_break, flush)?;
+            let duration = parse_timestamp(end_break)? - parse_timestamp(start_break)?;
+            breaks.push(Break {
+                start_time: start_break.clone(),
+                end_time: end_break.clone(),
+                duration: duration.num_seconds(),
+                latitude: location.latitude,
+                longitude: location.longitude,
+                offset: location.offset,
+                interpolated: location.interpolated,
+            });
+        }
+        // the rest fields locate the first break, a shift without break leaves them empty
+        let rest = match shift.breaks.first() {
+            Some((start_break, _)) => self.locate(&shift.truck_id, start_break, flush)?,
+            None => Location::default(),
+        };
+        let rest_time: i64 = breaks.iter().map(|rest| rest.duration).sum();
+
+        let (start_time, end_time) = (event_time(start_day)?, event_time(end_day)?);
+        let metrics = ShiftMetrics::compute(
+            (end_time - start_time) / 1_000_000,
+            rest_time,
+            &self.positions_between(&shift.truck_id, start_time, end_time),
+        );
+
+        Some(Report {
+            driver_id: driver.driver_id,
+            first_name: driver.first_name,
+            last_name: driver.last_name,
+            email: driver.email,
+            phone: driver.phone,
+            truck_id: truck.truck_id,
+            immatriculation: truck.immatriculation,
+            start_time: start_day.clone(),
+            end_time: end_day.clone(),
+            rest_time: rest_time.to_string(),
+            latitude_start: start.latitude,
+            longitude_start: start.longitude,
+            timestamp_start: start.timestamp,
+            latitude_end: end.latitude,
+            longitude_end: end.longitude,
+            timestamp_end: end.timestamp,
+            latitude_rest: rest.latitude,
+            longitude_rest: rest.longitude,
+            timestamp_rest: rest.timestamp,
+            shift_duration: metrics.shift_duration,
+            driving_duration: metrics.driving_duration,
+            break_duration: metrics.break_duration,
+            distance_km: metrics.distance_km,
+            average_speed_kmh: metrics.average_speed_kmh,
+            max_speed_kmh: metrics.max_speed_kmh,
+            offset_start: start.offset,
+            interpolated_start: start.interpolated,
+            offset_end: end.offset,
+            interpolated_end: end.interpolated,
+            offset_rest: rest.offset,
+            interpolated_rest: rest.interpolated,
+            breaks,
+        })
+    }
+}
+
+fn geofence_event(kind: GeofenceEventType, position: &Position, id: &str, name: &str, dwell_duration: Option<i64>) -> GeofenceEvent {
+    GeofenceEvent {
+        kind,
+        truck_id: position.truck_id.clone(),
+        geofence_id: id.to_string(),
+        geofence_name: name.to_string(),
+        timestamp: position.timestamp.clone(),
+        latitude: position.latitude,
+        longitude: position.longitude,
+        dwell_duration,
+    }
+}
+
+/// Deserializes an entry of the state store, logging the corrupted ones
+fn decode<T: DeserializeOwned>(table: Table, key: &str, value: &[u8]) -> Option<T> {
+    match serde_json::from_slice(value) {
+        Ok(value) => Some(value),
+        Err(err) => {
+            error!("Corrupted {} entry {}: {}", table.name(), key, err);
+            None
+        }
+    }
+}
+
+/// Event time of a timestamp, in microseconds since the epoch
+fn event_time(timestamp: &str) -> Option<i64> {
+    parse_timestamp(timestamp).map(|date| date.timestamp_micros())
+}
+
+/// Event time in a state key, padded so that the keys sort in event time order
+fn time_key(micros: i64) -> String {
+    format!("{:020}", micros)
+}
+
+/// Parses a timestamp sent either as microseconds since the epoch or as RFC 3339
+pub fn parse_timestamp(timestamp: &str) -> Option<DateTime<Utc>> {
+    match timestamp.parse::<i64>() {
+        Ok(micros) => DateTime::from_timestamp_micros(micros),
+        Err(_) => DateTime::parse_from_rfc3339(timestamp)
+            .ok()
+            .map(|date| date.with_timezone(&Utc)),
+    }
+}
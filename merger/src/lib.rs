@@ -0,0 +1,11 @@
+pub mod assignment;
+pub mod batch;
+pub mod compliance;
+pub mod config;
+pub mod consumer;
+pub mod duty;
+pub mod geofence;
+pub mod join;
+pub mod metrics;
+pub mod models;
+pub mod state;
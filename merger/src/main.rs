@@ -1,36 +1,82 @@
-mod config;
-mod consumer;
-mod models;
-
-use crate::config::CONFIG;
-use crate::consumer::consumer;
+use merger::batch::{batch, BatchInput};
+use merger::config::CONFIG;
+use merger::consumer::consumer;
+use merger::geofence::GeofenceRegistry;
+use merger::join::Joiner;
+use clap::{Parser, Subcommand};
+use log::{error, info};
 use rdkafka::config::ClientConfig;
-use models::entity::Entity;
-use models::position::Position;
-use models::time_registration::TimeRegistration;
-use chrono::Utc;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+#[derive(Debug, Parser)]
+#[clap(name = "merger", version)]
+struct App {
+    /// Consumes the Kafka topics when no command is given
+    #[clap(subcommand)]
+    action: Option<Command>,
+}
 
-fn main() {
-    let entity = Entity::newDriver("driver".to_string(), "D-123".to_string(), "baptiste".to_string(), "bronsin".to_string(), "email".to_string(), "+330650353421".to_string());
-    println!("{:?}", entity);
-    let entity = Entity::newTruck("truck".to_string(), "T-123".to_string(), "123AB456".to_string());
-    println!("{:?}", entity);
-    let time_registration = TimeRegistration::new("start".to_string(), "2025-01-09T08:41:00Z".to_string(), "D-123".to_string(), "T-123".to_string());
-    println!("{:?}", time_registration);
-    let pos = Position::new("T-123".to_string(), 123.0, 456.0, Utc::now().to_string());
-    println!("{:?}", pos);
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Consume the input topics and publish the reports to Kafka
+    Stream,
+    /// Join JSONL dumps of the input topics into a JSONL file of reports
+    Batch {
+        #[clap(long)]
+        entities: PathBuf,
+        #[clap(long)]
+        time_registrations: PathBuf,
+        #[clap(long)]
+        positions: PathBuf,
+        #[clap(long)]
+        out: PathBuf,
+    },
+}
 
+#[tokio::main]
+async fn main() -> ExitCode {
     env_logger::init(); // Initialize the logger
+    let app = App::parse();
 
-    // Create a new client configuration
-    let mut client_config = ClientConfig::new();
+    match app.action.unwrap_or(Command::Stream) {
+        Command::Stream => {
+            // Create a new client configuration
+            let mut client_config = ClientConfig::new();
 
-    // Set the group ID and bootstrap servers from the CONFIG struct
-    client_config
-        .set("group.id", &CONFIG.group_id)
-        .set("bootstrap.servers", &CONFIG.kafka_broker);
+            // Set the group ID and bootstrap servers from the CONFIG struct
+            client_config
+                .set("group.id", &CONFIG.group_id)
+                .set("bootstrap.servers", &CONFIG.kafka_broker);
 
-    // Call the consumer function with the configured client configuration
-    println!("Starting consumer...");
-    consumer(client_config);
+            // Call the consumer function with the configured client configuration
+            consumer(client_config).await
+        }
+        Command::Batch { entities, time_registrations, positions, out } => {
+            // joined with the same settings and geofences as the stream
+            let geofences = match GeofenceRegistry::load(&CONFIG.geofences) {
+                Ok(geofences) => geofences,
+                Err(err) => {
+                    error!("Unable to load the geofences: {}", err);
+                    return ExitCode::FAILURE;
+                }
+            };
+            let joiner = Joiner::new().with_config(&CONFIG.join).with_geofences(geofences);
+            let input = BatchInput {
+                entities: &entities,
+                time_registrations: &time_registrations,
+                positions: &positions,
+            };
+            match batch(&input, joiner, &out) {
+                Ok(count) => {
+                    info!("{} reports written to {}", count, out.display());
+                    ExitCode::SUCCESS
+                }
+                Err(err) => {
+                    error!("Batch failed: {}", err);
+                    ExitCode::FAILURE
+                }
+            }
+        }
+    }
 }
@@ -0,0 +1,66 @@
+use crate::models::position::Position;
+
+/// Mean radius of the Earth, in kilometres
+const EARTH_RADIUS_KM: f64 = 6371.0;
+
+/// Great-circle distance between two positions, in kilometres
+pub fn haversine_km(from: &Position, to: &Position) -> f64 {
+    distance_km((from.latitude, from.longitude), (to.latitude, to.longitude))
+}
+
+/// Great-circle distance between two (latitude, longitude) coordinates, in kilometres
+pub fn distance_km(from: (f64, f64), to: (f64, f64)) -> f64 {
+    let (lat_from, lat_to) = (from.0.to_radians(), to.0.to_radians());
+    let d_lat = lat_to - lat_from;
+    let d_lon = (to.1 - from.1).to_radians();
+    let a = (d_lat / 2.0).sin().powi(2) + lat_from.cos() * lat_to.cos() * (d_lon / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS_KM * a.sqrt().asin()
+}
+
+/// Figures of a shift derived from its time registrations and the positions of its truck
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ShiftMetrics {
+    /// Seconds from the start to the end of the day
+    pub shift_duration: i64,
+    /// Seconds of the shift outside of the breaks
+    pub driving_duration: i64,
+    /// Seconds of the breaks
+    pub break_duration: i64,
+    pub distance_km: f64,
+    /// Distance over the driving duration
+    pub average_speed_kmh: f64,
+    /// Greatest speed between two consecutive positions
+    pub max_speed_kmh: f64,
+}
+
+impl ShiftMetrics {
+    /// Computes the metrics of a shift from its durations in seconds and the positions of its
+    /// truck during the shift, with their event time in microseconds, in event time order
+    pub fn compute(shift_duration: i64, break_duration: i64, positions: &[(i64, Position)]) -> Self {
+        let driving_duration = (shift_duration - break_duration).max(0);
+        let mut distance_km = 0.0;
+        let mut max_speed_kmh: f64 = 0.0;
+        for pair in positions.windows(2) {
+            let ((from_time, from), (to_time, to)) = (&pair[0], &pair[1]);
+            let distance = haversine_km(from, to);
+            distance_km += distance;
+            let hours = (to_time - from_time) as f64 / 3_600_000_000.0;
+            if hours > 0.0 {
+                max_speed_kmh = max_speed_kmh.max(distance / hours);
+            }
+        }
+        let average_speed_kmh = if driving_duration > 0 {
+            distance_km / (driving_duration as f64 / 3600.0)
+        } else {
+            0.0
+        };
+        Self {
+            shift_duration,
+            driving_duration,
+            break_duration,
+            distance_km,
+            average_speed_kmh,
+            max_speed_kmh,
+        }
+    }
+}
@@ -1,4 +1 @@
-pub mod entity;
-pub mod position;
-pub mod time_registration;
-pub mod report;
\ No newline at end of file
+pub use kafkamion_model::{alert, anomaly, compliance, entity, erasure, geofence, position, report, time_registration};
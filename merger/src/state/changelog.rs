@@ -0,0 +1,90 @@
+use super::{StateStore, Table};
+use kafkamion_broker::{BrokerError, Record, Sink, Source};
+use log::error;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Store writing every mutation of an inner store to a compacted changelog topic, keyed by
+/// `<table>/<key>`, so that the state can be rebuilt on another disk or another instance
+pub struct ChangelogStore {
+    inner: Box<dyn StateStore>,
+    sink: Arc<dyn Sink + Send + Sync>,
+    topic: String,
+    /// The first mutation that could not be logged since the last `take_failure`
+    failure: Option<BrokerError>,
+}
+
+impl ChangelogStore {
+    pub fn new(inner: Box<dyn StateStore>, sink: Arc<dyn Sink + Send + Sync>, topic: &str) -> Self {
+        Self {
+            inner,
+            sink,
+            topic: topic.to_string(),
+            failure: None,
+        }
+    }
+
+    fn fail(&mut self, message: String) {
+        error!("{}", message);
+        self.failure.get_or_insert(BrokerError(message));
+    }
+}
+
+fn changelog_key(table: Table, key: &str) -> String {
+    format!("{}/{}", table.name(), key)
+}
+
+/// Applies a changelog record to the store, a record without payload deleting its key
+pub fn apply(store: &mut (impl StateStore + ?Sized), record: &Record) {
+    let entry = record.key.as_deref().and_then(|key| key.split_once('/'));
+    let Some((table, key)) = entry.and_then(|(table, key)| Some((Table::from_name(table)?, key))) else {
+        error!("Invalid changelog key at offset {}: {:?}", record.offset, record.key);
+        return;
+    };
+    match &record.payload {
+        Some(value) => store.put(table, key, value.clone()),
+        None => store.delete(table, key),
+    }
+}
+
+impl StateStore for ChangelogStore {
+    fn get(&self, table: Table, key: &str) -> Option<Vec<u8>> {
+        self.inner.get(table, key)
+    }
+
+    fn put(&mut self, table: Table, key: &str, value: Vec<u8>) {
+        if let Err(err) = self.sink.send(&self.topic, &changelog_key(table, key), &value) {
+            self.fail(format!("Unable to log the {} entry {}: {}", table.name(), key, err));
+        }
+        self.inner.put(table, key, value);
+    }
+
+    fn delete(&mut self, table: Table, key: &str) {
+        if let Err(err) = self.sink.send_tombstone(&self.topic, &changelog_key(table, key)) {
+            self.fail(format!("Unable to log the deletion of the {} entry {}: {}", table.name(), key, err));
+        }
+        self.inner.delete(table, key);
+    }
+
+    fn scan(&self, table: Table, prefix: &str) -> Vec<(String, Vec<u8>)> {
+        self.inner.scan(table, prefix)
+    }
+
+    fn persist(&mut self) {
+        self.inner.persist();
+    }
+
+    fn take_failure(&mut self) -> Option<BrokerError> {
+        self.failure.take()
+    }
+
+    /// Clears the inner store only, the changelog keeping the entries to restore
+    fn clear(&mut self) {
+        self.inner.clear();
+    }
+
+    /// Restores the inner store only, the records being in the changelog already
+    fn restore(&mut self, source: &mut dyn Source, poll_timeout: Duration) -> usize {
+        self.inner.restore(source, poll_timeout)
+    }
+}
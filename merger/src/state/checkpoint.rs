@@ -0,0 +1,60 @@
+use crate::config::{StateConfig, StoreKind};
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::io;
+use std::path::PathBuf;
+
+/// Changelog offsets the on-disk store reflects, written next to it when the merger stops
+/// cleanly. While the merger runs, the store may hold the effects of a transaction that is not
+/// committed, so the checkpoint is removed on startup and a store without checkpoint is rebuilt
+/// from the whole changelog.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Checkpoint {
+    /// Offset following the last changelog record applied, by changelog partition
+    pub offsets: BTreeMap<i32, i64>,
+    /// Input partitions assigned to the merger when it stopped
+    pub assigned: BTreeSet<(String, i32)>,
+}
+
+impl Checkpoint {
+    /// Where the checkpoint of the store is kept, `None` for a store lost when the merger stops
+    fn path(config: &StateConfig) -> Option<PathBuf> {
+        match config.store {
+            StoreKind::Memory => None,
+            StoreKind::Sled => Some(PathBuf::from(format!("{}.checkpoint", config.path))),
+        }
+    }
+
+    /// Reads the checkpoint of the store and removes it. A checkpoint left behind would outlive
+    /// the changes of this run, so failing to remove it is an error.
+    pub fn take(config: &StateConfig) -> io::Result<Option<Checkpoint>> {
+        let Some(path) = Self::path(config) else {
+            return Ok(None);
+        };
+        let json = match std::fs::read_to_string(&path) {
+            Ok(json) => json,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err),
+        };
+        std::fs::remove_file(&path)?;
+        match serde_json::from_str(&json) {
+            Ok(checkpoint) => Ok(Some(checkpoint)),
+            Err(err) => {
+                warn!("Ignoring the invalid checkpoint {}: {}", path.display(), err);
+                Ok(None)
+            }
+        }
+    }
+
+    /// Writes the checkpoint next to the store, once the changes of the store are on disk
+    pub fn save(&self, config: &StateConfig) -> io::Result<()> {
+        let Some(path) = Self::path(config) else {
+            return Ok(());
+        };
+        // written whole or not at all
+        let written = path.with_extension("checkpoint.tmp");
+        std::fs::write(&written, serde_json::to_vec(self)?)?;
+        std::fs::rename(written, path)
+    }
+}
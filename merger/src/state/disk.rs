@@ -0,0 +1,65 @@
+use super::{StateStore, Table};
+use log::error;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// State stored on disk in an embedded sled database, one tree per table, so that a restarted
+/// merger resumes the join where it stopped
+pub struct SledStore {
+    db: sled::Db,
+    trees: HashMap<Table, sled::Tree>,
+}
+
+impl SledStore {
+    pub fn open(path: impl AsRef<Path>) -> sled::Result<Self> {
+        let db = sled::open(path)?;
+        let mut trees = HashMap::new();
+        for table in Table::ALL {
+            trees.insert(table, db.open_tree(table.name())?);
+        }
+        Ok(Self { db, trees })
+    }
+
+    fn tree(&self, table: Table) -> &sled::Tree {
+        &self.trees[&table]
+    }
+}
+
+impl StateStore for SledStore {
+    fn get(&self, table: Table, key: &str) -> Option<Vec<u8>> {
+        self.tree(table)
+            .get(key)
+            .expect("State store read failed")
+            .map(|value| value.to_vec())
+    }
+
+    fn put(&mut self, table: Table, key: &str, value: Vec<u8>) {
+        self.tree(table).insert(key, value).expect("State store write failed");
+    }
+
+    fn delete(&mut self, table: Table, key: &str) {
+        self.tree(table).remove(key).expect("State store write failed");
+    }
+
+    fn scan(&self, table: Table, prefix: &str) -> Vec<(String, Vec<u8>)> {
+        self.tree(table)
+            .scan_prefix(prefix)
+            .map(|entry| {
+                let (key, value) = entry.expect("State store read failed");
+                (String::from_utf8_lossy(&key).into_owned(), value.to_vec())
+            })
+            .collect()
+    }
+
+    fn persist(&mut self) {
+        self.db.flush().expect("State store flush failed");
+    }
+}
+
+impl Drop for SledStore {
+    fn drop(&mut self) {
+        if let Err(err) = self.db.flush() {
+            error!("Unable to flush the state store: {}", err);
+        }
+    }
+}
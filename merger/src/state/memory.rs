@@ -0,0 +1,41 @@
+use super::{StateStore, Table};
+use std::collections::{BTreeMap, HashMap};
+
+/// State kept in memory, lost when the merger stops
+#[derive(Debug, Default)]
+pub struct InMemoryStore {
+    tables: HashMap<Table, BTreeMap<String, Vec<u8>>>,
+}
+
+impl InMemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl StateStore for InMemoryStore {
+    fn get(&self, table: Table, key: &str) -> Option<Vec<u8>> {
+        self.tables.get(&table)?.get(key).cloned()
+    }
+
+    fn put(&mut self, table: Table, key: &str, value: Vec<u8>) {
+        self.tables.entry(table).or_default().insert(key.to_string(), value);
+    }
+
+    fn delete(&mut self, table: Table, key: &str) {
+        if let Some(entries) = self.tables.get_mut(&table) {
+            entries.remove(key);
+        }
+    }
+
+    fn scan(&self, table: Table, prefix: &str) -> Vec<(String, Vec<u8>)> {
+        let Some(entries) = self.tables.get(&table) else {
+            return Vec::new();
+        };
+        entries
+            .range(prefix.to_string()..)
+            .take_while(|(key, _)| key.starts_with(prefix))
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect()
+    }
+}
@@ -0,0 +1,132 @@
+use crate::config::{StateConfig, StoreKind};
+use kafkamion_broker::{BrokerError, Source};
+use log::error;
+use std::time::Duration;
+
+pub mod changelog;
+pub mod checkpoint;
+pub mod disk;
+pub mod memory;
+
+pub use changelog::ChangelogStore;
+pub use checkpoint::Checkpoint;
+pub use disk::SledStore;
+pub use memory::InMemoryStore;
+
+/// Tables of the join state
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Table {
+    Drivers,
+    Trucks,
+    /// Time registrations not yet grouped into a shift, keyed by `driver_id/event time/type`
+    Registrations,
+    /// Finished shifts still waiting for an entity or a position, keyed by `driver_id/event time`
+    ClosedShifts,
+    /// Positions keyed by `truck_id/event time`
+    Positions,
+    /// Greatest event time read from each input partition, keyed by `topic/partition`
+    Watermarks,
+    /// Working days of the drivers checked against the driving hours rules, keyed by
+    /// `driver_id/event time`
+    WorkDays,
+    /// Geofences each truck is inside of as of its latest position, keyed by `truck_id`
+    Geofences,
+    /// Drivers on shift on each truck and trucks of each driver, keyed by `truck/truck_id` and
+    /// `driver/driver_id`
+    Assignments,
+    /// Drivers erased under the right to erasure, keyed by `driver_id`
+    Erasures,
+    /// Latest position of each truck checked for a driver on shift, keyed by `truck_id`
+    Attendance,
+}
+
+impl Table {
+    pub const ALL: [Table; 11] = [
+        Table::Drivers,
+        Table::Trucks,
+        Table::Registrations,
+        Table::ClosedShifts,
+        Table::Positions,
+        Table::Watermarks,
+        Table::WorkDays,
+        Table::Geofences,
+        Table::Assignments,
+        Table::Erasures,
+        Table::Attendance,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Table::Drivers => "drivers",
+            Table::Trucks => "trucks",
+            Table::Registrations => "registrations",
+            Table::ClosedShifts => "closed_shifts",
+            Table::Positions => "positions",
+            Table::Watermarks => "watermarks",
+            Table::WorkDays => "work_days",
+            Table::Geofences => "geofences",
+            Table::Assignments => "assignments",
+            Table::Erasures => "erasures",
+            Table::Attendance => "attendance",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Table> {
+        Table::ALL.into_iter().find(|table| table.name() == name)
+    }
+}
+
+/// Key/value storage of the join state, the values being JSON documents.
+///
+/// A storage failure leaves the join inconsistent, so the implementations panic instead of
+/// returning errors.
+pub trait StateStore: Send {
+    fn get(&self, table: Table, key: &str) -> Option<Vec<u8>>;
+
+    fn put(&mut self, table: Table, key: &str, value: Vec<u8>);
+
+    fn delete(&mut self, table: Table, key: &str);
+
+    /// Entries of the table whose key starts with `prefix`, ordered by key
+    fn scan(&self, table: Table, prefix: &str) -> Vec<(String, Vec<u8>)>;
+
+    /// Deletes every entry of every table
+    fn clear(&mut self) {
+        for table in Table::ALL {
+            for (key, _) in self.scan(table, "") {
+                self.delete(table, &key);
+            }
+        }
+    }
+
+    /// Writes the changes not yet on disk, if the store keeps any
+    fn persist(&mut self) {}
+
+    /// The first write the store failed to replicate since the last call, which must not be
+    /// committed as the state it leads to could not be restored
+    fn take_failure(&mut self) -> Option<BrokerError> {
+        None
+    }
+
+    /// Applies the records of a changelog until the source has nothing left to give, waiting up
+    /// to `poll_timeout` for each. Returns the number of records read.
+    fn restore(&mut self, source: &mut dyn Source, poll_timeout: Duration) -> usize {
+        let mut count = 0;
+        while let Some(received) = source.poll(poll_timeout) {
+            count += 1;
+            match received {
+                Ok(record) => changelog::apply(self, &record),
+                Err(err) => error!("Error while reading the changelog: {}", err),
+            }
+        }
+        count
+    }
+}
+
+/// Opens the store chosen in the `state` section of the configuration
+pub fn open(config: &StateConfig) -> Box<dyn StateStore> {
+    match config.store {
+        StoreKind::Memory => Box::new(InMemoryStore::new()),
+        StoreKind::Sled => Box::new(SledStore::open(&config.path).expect("Unable to open the state store")),
+    }
+}
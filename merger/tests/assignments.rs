@@ -0,0 +1,151 @@
+mod common;
+
+use chrono::Duration;
+use common::{timestamp, PARTITION};
+use merger::join::{Joiner, Output};
+use merger::models::alert::{Alert, AlertType};
+use merger::models::entity::Entity;
+use merger::models::position::Position;
+use merger::models::time_registration::{TimeRegistration, TimeRegistrationType};
+
+/// A joiner knowing drivers D-1 and D-2 and trucks T-1 and T-2
+fn joiner() -> Joiner {
+    let mut joiner = Joiner::new().with_allowed_lateness(Duration::hours(2));
+    for id in ["D-1", "D-2"] {
+        joiner.on_entity(Entity::Driver(common::driver(id)));
+    }
+    for id in ["T-1", "T-2"] {
+        joiner.on_entity(Entity::Truck(common::truck(id)));
+    }
+    joiner
+}
+
+fn register(joiner: &mut Joiner, kind: TimeRegistrationType, driver_id: &str, truck_id: &str, minutes: i64) {
+    let registration = TimeRegistration {
+        driver_id: driver_id.to_string(),
+        truck_id: truck_id.to_string(),
+        ..common::registration(kind, minutes)
+    };
+    joiner.on_time_registration(PARTITION, registration).unwrap();
+}
+
+/// Registers a position of truck T-1 `km` kilometres north of Paris
+fn drive(joiner: &mut Joiner, minutes: i64, km: f64) {
+    let position = Position {
+        latitude: 48.8566 + km / 111.2,
+        ..common::position(minutes)
+    };
+    joiner.on_position(PARTITION, position).unwrap();
+}
+
+fn alerts(joiner: &mut Joiner) -> Vec<Alert> {
+    joiner
+        .take_outputs()
+        .into_iter()
+        .filter_map(|output| match output {
+            Output::Alert(alert) => Some(alert),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn successive_shifts_on_a_truck_are_not_alerted() {
+    let mut joiner = joiner();
+    register(&mut joiner, TimeRegistrationType::StartDay, "D-1", "T-1", 0);
+    register(&mut joiner, TimeRegistrationType::EndDay, "D-1", "T-1", 480);
+    register(&mut joiner, TimeRegistrationType::StartDay, "D-2", "T-1", 540);
+    register(&mut joiner, TimeRegistrationType::StartDay, "D-1", "T-2", 600);
+    assert!(alerts(&mut joiner).is_empty());
+}
+
+#[test]
+fn a_truck_with_two_drivers_on_shift_is_double_booked() {
+    let mut joiner = joiner();
+    register(&mut joiner, TimeRegistrationType::StartDay, "D-1", "T-1", 0);
+    register(&mut joiner, TimeRegistrationType::StartDay, "D-2", "T-1", 60);
+
+    let alerts = alerts(&mut joiner);
+    assert_eq!(alerts.len(), 1);
+    assert_eq!(alerts[0].kind, AlertType::DoubleBookedTruck);
+    assert_eq!(alerts[0].truck_ids, ["T-1"]);
+    assert_eq!(alerts[0].driver_ids, ["D-1", "D-2"]);
+    assert_eq!(alerts[0].timestamp, timestamp(60));
+}
+
+#[test]
+fn an_end_day_arriving_before_its_start_day_ends_the_assignment() {
+    let mut joiner = joiner();
+    register(&mut joiner, TimeRegistrationType::EndDay, "D-1", "T-1", 480);
+    register(&mut joiner, TimeRegistrationType::StartDay, "D-2", "T-1", 540);
+    register(&mut joiner, TimeRegistrationType::StartDay, "D-1", "T-1", 0);
+    assert!(alerts(&mut joiner).is_empty());
+
+    register(&mut joiner, TimeRegistrationType::StartDay, "D-1", "T-1", 600);
+    assert_eq!(alerts(&mut joiner).len(), 1);
+}
+
+#[test]
+fn a_driver_on_shift_on_two_trucks_is_alerted() {
+    let mut joiner = joiner();
+    register(&mut joiner, TimeRegistrationType::StartDay, "D-1", "T-1", 0);
+    register(&mut joiner, TimeRegistrationType::StartDay, "D-1", "T-2", 30);
+
+    let alerts = alerts(&mut joiner);
+    let kinds: Vec<AlertType> = alerts.iter().map(|alert| alert.kind).collect();
+    assert_eq!(kinds, [AlertType::DriverOnTwoTrucks]);
+    assert_eq!(alerts[0].truck_ids, ["T-1", "T-2"]);
+    assert_eq!(alerts[0].driver_ids, ["D-1"]);
+}
+
+#[test]
+fn a_truck_moving_with_no_driver_is_alerted_once_per_move() {
+    let mut joiner = joiner();
+    drive(&mut joiner, 0, 0.0);
+    // drifting by 100 metres
+    drive(&mut joiner, 5, 0.1);
+    assert!(alerts(&mut joiner).is_empty());
+
+    drive(&mut joiner, 10, 5.0);
+    drive(&mut joiner, 15, 10.0);
+    let alerts = alerts(&mut joiner);
+    assert_eq!(alerts.len(), 1);
+    assert_eq!(alerts[0].kind, AlertType::TruckMovingWithoutDriver);
+    assert_eq!(alerts[0].truck_ids, ["T-1"]);
+    assert!(alerts[0].driver_ids.is_empty());
+    assert_eq!(alerts[0].timestamp, timestamp(10));
+}
+
+#[test]
+fn a_truck_moving_with_a_driver_on_shift_is_not_alerted() {
+    let mut joiner = joiner();
+    drive(&mut joiner, 0, 0.0);
+    register(&mut joiner, TimeRegistrationType::StartDay, "D-1", "T-1", 1);
+    drive(&mut joiner, 10, 5.0);
+    register(&mut joiner, TimeRegistrationType::EndDay, "D-1", "T-1", 60);
+    drive(&mut joiner, 61, 6.0);
+    assert!(alerts(&mut joiner).is_empty());
+
+    drive(&mut joiner, 70, 15.0);
+    assert_eq!(alerts(&mut joiner).len(), 1);
+}
+
+#[test]
+fn positions_arriving_before_their_registrations_are_checked_once_final() {
+    let mut joiner = joiner();
+    joiner.track(PARTITION);
+    drive(&mut joiner, 0, 0.0);
+    drive(&mut joiner, 10, 5.0);
+    drive(&mut joiner, 20, 10.0);
+    register(&mut joiner, TimeRegistrationType::StartDay, "D-1", "T-1", 0);
+    register(&mut joiner, TimeRegistrationType::EndDay, "D-1", "T-1", 30);
+    drive(&mut joiner, 40, 15.0);
+    assert!(alerts(&mut joiner).is_empty());
+
+    // the watermark minus the allowed lateness passes the positions by the tolerance
+    drive(&mut joiner, 200, 15.0);
+    let alerts = alerts(&mut joiner);
+    assert_eq!(alerts.len(), 1);
+    assert_eq!(alerts[0].kind, AlertType::TruckMovingWithoutDriver);
+    assert_eq!(alerts[0].timestamp, timestamp(40));
+}
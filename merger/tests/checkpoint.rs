@@ -0,0 +1,39 @@
+use merger::config::{StateConfig, StoreKind};
+use merger::state::Checkpoint;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// A sled store under the temporary directory, unique to each test
+fn sled_config(name: &str) -> StateConfig {
+    let path = std::env::temp_dir().join(format!("merger-checkpoint-{}-{}", name, std::process::id()));
+    StateConfig {
+        store: StoreKind::Sled,
+        path: path.display().to_string(),
+        restore_poll_secs: 5,
+        restore_timeout_secs: 300,
+    }
+}
+
+#[test]
+fn a_checkpoint_is_taken_once() {
+    let config = sled_config("taken");
+    let checkpoint = Checkpoint {
+        offsets: BTreeMap::from([(0, 42), (1, 7)]),
+        assigned: BTreeSet::from([("position_topic".to_string(), 0), ("time_registration_topic".to_string(), 0)]),
+    };
+    checkpoint.save(&config).unwrap();
+
+    assert_eq!(Checkpoint::take(&config).unwrap(), Some(checkpoint));
+    // the state changes once the merger runs, a crash must rebuild it
+    assert_eq!(Checkpoint::take(&config).unwrap(), None);
+}
+
+#[test]
+fn a_store_in_memory_has_no_checkpoint() {
+    let config = StateConfig {
+        store: StoreKind::Memory,
+        ..sled_config("memory")
+    };
+    Checkpoint::default().save(&config).unwrap();
+    assert_eq!(Checkpoint::take(&config).unwrap(), None);
+    assert_eq!(Checkpoint::take(&sled_config("memory")).unwrap(), None);
+}
@@ -0,0 +1,71 @@
+//! Fixtures shared by the join tests, each test file using its own part of them
+#![allow(dead_code)]
+
+use merger::join::Joiner;
+use merger::models::entity::{Driver, Entity, Truck};
+use merger::models::position::Position;
+use merger::models::time_registration::{TimeRegistration, TimeRegistrationType};
+
+/// Monday 6 January 2025, 6 am, in microseconds
+pub const START: i64 = 1_736_143_200_000_000;
+
+/// Input partition the events are read from
+pub const PARTITION: &str = "input-0";
+
+/// Timestamp `minutes` after `START`, in microseconds
+pub fn timestamp(minutes: i64) -> String {
+    (START + minutes * 60_000_000).to_string()
+}
+
+/// Driver Jane Doe at version 0, the other values being set with the struct update syntax
+pub fn driver(driver_id: &str) -> Driver {
+    Driver {
+        driver_id: driver_id.to_string(),
+        first_name: "Jane".to_string(),
+        last_name: "Doe".to_string(),
+        email: "jane.doe@example.com".to_string(),
+        phone: "+33600000000".to_string(),
+        version: 0,
+    }
+}
+
+/// Truck AB-123-CD at version 0
+pub fn truck(truck_id: &str) -> Truck {
+    Truck {
+        truck_id: truck_id.to_string(),
+        immatriculation: "AB-123-CD".to_string(),
+        version: 0,
+    }
+}
+
+/// Makes a joiner know driver D-1 and truck T-1
+pub fn crew(mut joiner: Joiner) -> Joiner {
+    joiner.on_entity(Entity::Driver(driver("D-1")));
+    joiner.on_entity(Entity::Truck(truck("T-1")));
+    joiner
+}
+
+/// A joiner with the default settings knowing driver D-1 and truck T-1
+pub fn joiner() -> Joiner {
+    crew(Joiner::new())
+}
+
+/// A position of truck T-1 `minutes` after `START`, north of Paris by 0.001 degree a minute
+pub fn position(minutes: i64) -> Position {
+    Position {
+        truck_id: "T-1".to_string(),
+        latitude: 48.8566 + minutes as f64 / 1000.0,
+        longitude: 2.3522,
+        timestamp: timestamp(minutes),
+    }
+}
+
+/// A time registration of driver D-1 on truck T-1 `minutes` after `START`
+pub fn registration(kind: TimeRegistrationType, minutes: i64) -> TimeRegistration {
+    TimeRegistration {
+        kind,
+        timestamp: timestamp(minutes),
+        driver_id: "D-1".to_string(),
+        truck_id: "T-1".to_string(),
+    }
+}
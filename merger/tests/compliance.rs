@@ -0,0 +1,147 @@
+use chrono::{DateTime, Duration};
+use merger::compliance::{evaluate, WorkDay};
+use merger::models::compliance::{Rule, Violation};
+
+/// Monday 6 January 2025, 6 am
+const MONDAY: &str = "2025-01-06T06:00:00Z";
+
+/// A working day starting `days` after `MONDAY`, alternating driving and breaks of the given
+/// minutes, starting and ending with driving
+fn day(days: i64, plan: &[i64]) -> WorkDay {
+    let start = DateTime::parse_from_rfc3339(MONDAY).unwrap() + Duration::days(days);
+    let start = start.timestamp_micros();
+    let mut time = start;
+    let mut breaks = Vec::new();
+    for (index, minutes) in plan.iter().enumerate() {
+        let end = time + minutes * 60_000_000;
+        if index % 2 == 1 {
+            breaks.push((time, end));
+        }
+        time = end;
+    }
+    WorkDay { start, end: time, breaks }
+}
+
+/// Evaluates the days in order, each with the previous ones as history, and keeps the
+/// violations of `rule`
+fn replay(days: &[WorkDay], rule: Rule) -> Vec<Violation> {
+    days.iter()
+        .enumerate()
+        .flat_map(|(index, day)| evaluate("D-1", day, &days[..index]))
+        .filter(|violation| violation.rule == rule)
+        .collect()
+}
+
+#[test]
+fn a_45_minutes_break_after_4_hours_30_of_driving_is_compliant() {
+    assert!(replay(&[day(0, &[270, 45, 240])], Rule::BreakAfterDriving).is_empty());
+}
+
+#[test]
+fn driving_5_hours_without_break_is_a_violation() {
+    let violations = replay(&[day(0, &[300, 45, 180])], Rule::BreakAfterDriving);
+    assert_eq!(violations.len(), 1);
+    assert_eq!(violations[0].driver_id, "D-1");
+    assert_eq!((violations[0].limit, violations[0].actual), (4 * 3600 + 1800, 5 * 3600));
+}
+
+#[test]
+fn a_break_split_in_15_then_30_minutes_is_compliant() {
+    assert!(replay(&[day(0, &[120, 15, 120, 30, 240])], Rule::BreakAfterDriving).is_empty());
+}
+
+#[test]
+fn a_break_split_in_30_then_15_minutes_is_a_violation() {
+    let violations = replay(&[day(0, &[120, 30, 120, 15, 60])], Rule::BreakAfterDriving);
+    assert_eq!(violations.len(), 1);
+    assert_eq!(violations[0].actual, 5 * 3600);
+}
+
+#[test]
+fn short_breaks_do_not_reset_the_driving_time() {
+    let violations = replay(&[day(0, &[180, 10, 120, 45, 60])], Rule::BreakAfterDriving);
+    assert_eq!(violations.len(), 1);
+    assert_eq!(violations[0].actual, 5 * 3600);
+}
+
+#[test]
+fn driving_more_than_10_hours_a_day_is_a_violation() {
+    let violations = replay(&[day(0, &[270, 45, 270, 45, 90])], Rule::DailyDriving);
+    assert_eq!(violations.len(), 1);
+    assert_eq!((violations[0].limit, violations[0].actual), (10 * 3600, 10 * 3600 + 1800));
+}
+
+#[test]
+fn driving_10_hours_is_allowed_twice_a_week() {
+    let extended = |days| day(days, &[270, 45, 270, 45, 60]);
+    assert!(replay(&[extended(0), extended(2)], Rule::DailyDriving).is_empty());
+
+    let violations = replay(&[extended(0), extended(2), extended(4)], Rule::DailyDriving);
+    assert_eq!(violations.len(), 1);
+    assert_eq!((violations[0].limit, violations[0].actual), (9 * 3600, 10 * 3600));
+
+    // the extended days of the previous week do not count
+    assert!(replay(&[extended(4), extended(7), extended(9)], Rule::DailyDriving).is_empty());
+}
+
+#[test]
+fn a_rest_of_11_hours_between_two_days_is_compliant() {
+    // 9 hours 45 of work from 6 am, then 11 hours of rest up to 2:45 am
+    let first = day(0, &[270, 45, 270]);
+    let mut second = day(1, &[270, 45, 270]);
+    let shift = first.end + 11 * 3_600_000_000 - second.start;
+    second.start += shift;
+    second.end += shift;
+    second.breaks = second.breaks.iter().map(|(start, end)| (start + shift, end + shift)).collect();
+    assert!(replay(&[first, second], Rule::DailyRest).is_empty());
+}
+
+#[test]
+fn a_rest_shorter_than_11_hours_is_a_violation() {
+    let first = day(0, &[270, 45, 270]);
+    let mut second = day(1, &[270, 45, 270]);
+    let shift = first.end + 10 * 3_600_000_000 - second.start;
+    second.start += shift;
+    second.end += shift;
+    second.breaks = second.breaks.iter().map(|(start, end)| (start + shift, end + shift)).collect();
+
+    let violations = replay(&[first.clone(), second.clone()], Rule::DailyRest);
+    assert_eq!(violations.len(), 1);
+    assert_eq!((violations[0].limit, violations[0].actual), (11 * 3600, 10 * 3600));
+    assert_eq!(violations[0].period_start, first.end.to_string());
+    assert_eq!(violations[0].period_end, second.start.to_string());
+
+    // a day arriving after the next one is checked against it too
+    assert_eq!(replay(&[second, first], Rule::DailyRest).len(), 1);
+}
+
+#[test]
+fn driving_more_than_56_hours_a_week_is_a_violation() {
+    let week: Vec<WorkDay> = (0..6).map(|days| day(days, &[270, 45, 270])).collect();
+    // five days of 9 hours
+    assert!(replay(&week[..5], Rule::WeeklyDriving).is_empty());
+
+    // a sixth one makes 54 hours, a seventh 63
+    let mut days = week;
+    days.push(day(6, &[270, 45, 270]));
+    let violations = replay(&days, Rule::WeeklyDriving);
+    assert_eq!(violations.len(), 1);
+    assert_eq!((violations[0].limit, violations[0].actual), (56 * 3600, 63 * 3600));
+}
+
+#[test]
+fn driving_more_than_90_hours_in_two_weeks_is_a_violation() {
+    // 45 hours each week
+    let mut days: Vec<WorkDay> = (0..5).chain(7..12).map(|days| day(days, &[270, 45, 270])).collect();
+    assert!(replay(&days, Rule::FortnightlyDriving).is_empty());
+
+    days.push(day(12, &[60]));
+    let violations = replay(&days, Rule::FortnightlyDriving);
+    assert_eq!(violations.len(), 1);
+    assert_eq!((violations[0].limit, violations[0].actual), (90 * 3600, 91 * 3600));
+
+    // 36 hours the third week, which is checked with the second one only
+    let third: Vec<WorkDay> = (14..18).map(|days| day(days, &[270, 45, 270])).collect();
+    let days: Vec<WorkDay> = days.into_iter().chain(third).collect();
+    assert_eq!(replay(&days, Rule::FortnightlyDriving).len(), 1);
+}
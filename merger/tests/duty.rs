@@ -0,0 +1,197 @@
+mod common;
+
+use chrono::Duration;
+use common::{position, registration, timestamp, PARTITION};
+use merger::duty::transition;
+use merger::join::{Joiner, Output};
+use merger::models::anomaly::{Anomaly, DutyStatus};
+use merger::models::report::Report;
+use merger::models::time_registration::TimeRegistrationType;
+
+/// A joiner knowing driver D-1 and truck T-1, watermarked by a single partition with 2 hours of
+/// allowed lateness
+fn joiner() -> Joiner {
+    let mut joiner = common::crew(Joiner::new().with_allowed_lateness(Duration::hours(2)));
+    joiner.track(PARTITION);
+    joiner
+}
+
+/// Registers a position of the truck then a time registration `minutes` after `START`
+fn register(joiner: &mut Joiner, kind: TimeRegistrationType, minutes: i64) -> Vec<Report> {
+    let mut reports = joiner.on_position(PARTITION, position(minutes)).unwrap();
+    reports.extend(joiner.on_time_registration(PARTITION, registration(kind, minutes)).unwrap());
+    reports
+}
+
+/// Moves the watermark past the allowed lateness after `minutes`, with a position of the truck,
+/// so that the shift ending then is final. Returns the reports.
+fn settle(joiner: &mut Joiner, minutes: i64) -> Vec<Report> {
+    joiner.on_position(PARTITION, position(minutes + 121)).unwrap()
+}
+
+fn anomalies(joiner: &mut Joiner) -> Vec<Anomaly> {
+    joiner
+        .take_outputs()
+        .into_iter()
+        .filter_map(|output| match output {
+            Output::Anomaly(anomaly) => Some(anomaly),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn a_driver_goes_off_duty_on_shift_and_on_break() {
+    use DutyStatus::*;
+    use TimeRegistrationType::*;
+    assert_eq!(transition(OffDuty, StartDay), Ok(OnShift));
+    assert_eq!(transition(OnShift, StartBreak), Ok(OnBreak));
+    assert_eq!(transition(OnBreak, EndBreak), Ok(OnShift));
+    assert_eq!(transition(OnShift, EndDay), Ok(OffDuty));
+    for kind in [StartBreak, EndBreak, EndDay] {
+        assert!(transition(OffDuty, kind).is_err());
+    }
+    for kind in [StartDay, EndBreak] {
+        assert!(transition(OnShift, kind).is_err());
+    }
+    for kind in [StartDay, StartBreak, EndDay] {
+        assert!(transition(OnBreak, kind).is_err());
+    }
+}
+
+#[test]
+fn a_valid_shift_has_no_anomaly() {
+    let mut joiner = joiner();
+    register(&mut joiner, TimeRegistrationType::StartDay, 0);
+    register(&mut joiner, TimeRegistrationType::StartBreak, 240);
+    register(&mut joiner, TimeRegistrationType::EndBreak, 285);
+    register(&mut joiner, TimeRegistrationType::EndDay, 480);
+    let reports = settle(&mut joiner, 480);
+    assert_eq!(reports.len(), 1);
+    assert!(anomalies(&mut joiner).is_empty());
+}
+
+#[test]
+fn an_end_break_without_start_break_is_left_out_of_the_report() {
+    let mut joiner = joiner();
+    register(&mut joiner, TimeRegistrationType::StartDay, 0);
+    register(&mut joiner, TimeRegistrationType::StartBreak, 240);
+    register(&mut joiner, TimeRegistrationType::EndBreak, 285);
+    register(&mut joiner, TimeRegistrationType::EndBreak, 300);
+    register(&mut joiner, TimeRegistrationType::EndDay, 480);
+    let reports = settle(&mut joiner, 480);
+
+    assert_eq!(reports.len(), 1);
+    assert_eq!(reports[0].breaks.len(), 1);
+    assert_eq!(reports[0].breaks[0].duration, 45 * 60);
+    let anomalies = anomalies(&mut joiner);
+    assert_eq!(anomalies.len(), 1);
+    assert_eq!(anomalies[0].registration.kind, TimeRegistrationType::EndBreak);
+    assert_eq!(anomalies[0].registration.timestamp, timestamp(300));
+    assert_eq!(anomalies[0].status, DutyStatus::OnShift);
+    assert_eq!(anomalies[0].reason, "end_break without start_break");
+}
+
+#[test]
+fn a_break_open_at_the_end_day_is_left_out_of_the_report() {
+    let mut joiner = joiner();
+    register(&mut joiner, TimeRegistrationType::StartDay, 0);
+    register(&mut joiner, TimeRegistrationType::StartBreak, 240);
+    register(&mut joiner, TimeRegistrationType::EndBreak, 285);
+    register(&mut joiner, TimeRegistrationType::StartBreak, 400);
+    register(&mut joiner, TimeRegistrationType::EndDay, 480);
+    let reports = settle(&mut joiner, 480);
+
+    assert_eq!(reports.len(), 1);
+    assert_eq!(reports[0].breaks.len(), 1);
+    let anomalies = anomalies(&mut joiner);
+    assert_eq!(anomalies.len(), 1);
+    assert_eq!(anomalies[0].registration.kind, TimeRegistrationType::StartBreak);
+    assert_eq!(anomalies[0].reason, "start_break without end_break");
+}
+
+#[test]
+fn an_end_day_before_the_start_day_is_flagged_once_expired() {
+    let mut joiner = joiner();
+    register(&mut joiner, TimeRegistrationType::EndDay, 0);
+    register(&mut joiner, TimeRegistrationType::StartDay, 60);
+    // the end_day could still be followed by a late start_day
+    assert!(anomalies(&mut joiner).is_empty());
+
+    register(&mut joiner, TimeRegistrationType::StartBreak, 300);
+    register(&mut joiner, TimeRegistrationType::EndBreak, 345);
+    register(&mut joiner, TimeRegistrationType::EndDay, 540);
+    let reports = settle(&mut joiner, 540);
+    assert_eq!(reports.len(), 1);
+    assert_eq!(reports[0].start_time, timestamp(60));
+    let anomalies = anomalies(&mut joiner);
+    assert_eq!(anomalies.len(), 1);
+    assert_eq!(anomalies[0].registration.kind, TimeRegistrationType::EndDay);
+    assert_eq!(anomalies[0].status, DutyStatus::OffDuty);
+    assert_eq!(anomalies[0].reason, "end_day without start_day");
+}
+
+#[test]
+fn two_start_day_in_a_row_leave_the_first_shift_unfinished() {
+    let mut joiner = joiner();
+    register(&mut joiner, TimeRegistrationType::StartDay, 0);
+    register(&mut joiner, TimeRegistrationType::StartBreak, 60);
+    register(&mut joiner, TimeRegistrationType::EndBreak, 90);
+    register(&mut joiner, TimeRegistrationType::StartDay, 120);
+    register(&mut joiner, TimeRegistrationType::StartBreak, 300);
+    register(&mut joiner, TimeRegistrationType::EndBreak, 345);
+    register(&mut joiner, TimeRegistrationType::EndDay, 540);
+    let reports = settle(&mut joiner, 540);
+
+    assert_eq!(reports.len(), 1);
+    assert_eq!(reports[0].start_time, timestamp(120));
+    assert_eq!(reports[0].breaks.len(), 1);
+    let anomalies = anomalies(&mut joiner);
+    let kinds: Vec<TimeRegistrationType> = anomalies.iter().map(|anomaly| anomaly.registration.kind).collect();
+    assert_eq!(
+        kinds,
+        [TimeRegistrationType::StartDay, TimeRegistrationType::StartBreak, TimeRegistrationType::EndBreak]
+    );
+    assert!(anomalies.iter().all(|anomaly| anomaly.reason == "shift not ended before the next start_day"));
+    assert_eq!(anomalies[0].status, DutyStatus::OffDuty);
+    assert_eq!(anomalies[1].status, DutyStatus::OnShift);
+}
+
+#[test]
+fn a_break_ending_after_the_end_day_arrived_is_in_the_shift() {
+    let mut joiner = joiner();
+    register(&mut joiner, TimeRegistrationType::StartDay, 0);
+    register(&mut joiner, TimeRegistrationType::StartBreak, 270);
+    register(&mut joiner, TimeRegistrationType::EndDay, 400);
+    // late by less than the allowed lateness
+    assert!(register(&mut joiner, TimeRegistrationType::EndBreak, 315).is_empty());
+
+    let reports = settle(&mut joiner, 400);
+    assert_eq!(reports.len(), 1);
+    assert_eq!(reports[0].breaks.len(), 1);
+    assert_eq!(reports[0].breaks[0].duration, 45 * 60);
+    let outputs = joiner.take_outputs();
+    assert!(
+        !outputs.iter().any(|output| matches!(output, Output::Anomaly(_) | Output::Violation(_))),
+        "{:?}",
+        outputs
+    );
+}
+
+#[test]
+fn a_shift_registered_in_reverse_order_is_reported_once_final() {
+    let mut joiner = joiner();
+    register(&mut joiner, TimeRegistrationType::StartDay, 0);
+    register(&mut joiner, TimeRegistrationType::EndDay, 330);
+    register(&mut joiner, TimeRegistrationType::EndBreak, 285);
+    register(&mut joiner, TimeRegistrationType::StartBreak, 240);
+    // a registration of the shift may still arrive until the watermark passes the lateness
+    assert!(joiner.on_position(PARTITION, position(450)).unwrap().is_empty());
+
+    let reports = settle(&mut joiner, 330);
+    assert_eq!(reports.len(), 1);
+    assert_eq!((reports[0].start_time.clone(), reports[0].end_time.clone()), (timestamp(0), timestamp(330)));
+    assert_eq!(reports[0].breaks.len(), 1);
+    assert_eq!(reports[0].breaks[0].start_time, timestamp(240));
+    assert!(anomalies(&mut joiner).is_empty());
+}
@@ -0,0 +1,145 @@
+mod common;
+
+use common::{position, registration, PARTITION};
+use kafkamion_broker::{InMemoryBroker, Sink};
+use merger::config::CONFIG;
+use merger::batch::{batch, BatchInput};
+use merger::consumer::pump;
+use merger::join::{Joiner, Rejection};
+use merger::models::entity::{Driver, Entity, Tombstone, Truck};
+use merger::models::report::Report;
+use merger::models::time_registration::TimeRegistrationType;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+fn driver(phone: &str, version: u64) -> Entity {
+    Entity::Driver(Driver {
+        phone: phone.to_string(),
+        version,
+        ..common::driver("D-1")
+    })
+}
+
+fn truck(immatriculation: &str, version: u64) -> Entity {
+    Entity::Truck(Truck {
+        immatriculation: immatriculation.to_string(),
+        version,
+        ..common::truck("T-1")
+    })
+}
+
+/// Joins a shift of driver D-1 on truck T-1 starting `days` after `START`, with a break
+fn shift(joiner: &mut Joiner, days: i64) -> Result<Vec<Report>, Rejection> {
+    let mut reports = Vec::new();
+    let plan = [
+        (TimeRegistrationType::StartDay, 0),
+        (TimeRegistrationType::StartBreak, 240),
+        (TimeRegistrationType::EndBreak, 285),
+        (TimeRegistrationType::EndDay, 480),
+    ];
+    for (kind, minutes) in plan {
+        let minutes = days * 24 * 60 + minutes;
+        reports.extend(joiner.on_position(PARTITION, position(minutes))?);
+        reports.extend(joiner.on_time_registration(PARTITION, registration(kind, minutes))?);
+    }
+    Ok(reports)
+}
+
+#[test]
+fn the_reports_after_an_update_have_its_values() {
+    let mut joiner = Joiner::new();
+    joiner.on_entity(driver("+33600000000", 1));
+    joiner.on_entity(truck("AB-123-CD", 1));
+    let reports = shift(&mut joiner, 0).unwrap();
+    assert_eq!(reports[0].phone, "+33600000000");
+
+    joiner.on_entity(driver("+33611111111", 2));
+    joiner.on_entity(truck("EF-456-GH", 2));
+    let reports = shift(&mut joiner, 1).unwrap();
+    assert_eq!(reports[0].phone, "+33611111111");
+    assert_eq!(reports[0].immatriculation, "EF-456-GH");
+}
+
+#[test]
+fn an_older_version_does_not_replace_the_known_one() {
+    let mut joiner = Joiner::new();
+    joiner.on_entity(driver("+33611111111", 2));
+    joiner.on_entity(driver("+33600000000", 1));
+    joiner.on_entity(truck("AB-123-CD", 0));
+    let reports = shift(&mut joiner, 0).unwrap();
+    assert_eq!(reports[0].phone, "+33611111111");
+}
+
+#[test]
+fn the_events_of_a_deleted_truck_are_rejected() {
+    let mut joiner = Joiner::new();
+    joiner.on_entity(driver("+33600000000", 1));
+    joiner.on_entity(truck("AB-123-CD", 1));
+    joiner.on_entity_deleted("T-1");
+    assert_eq!(shift(&mut joiner, 0), Err(Rejection::UnknownTruck("T-1".to_string())));
+
+    // the truck comes back with its next version
+    joiner.on_entity(truck("AB-123-CD", 2));
+    assert_eq!(shift(&mut joiner, 0).unwrap().len(), 1);
+}
+
+#[test]
+fn a_tombstone_of_the_entity_topic_deletes_its_entity() {
+    let topics = &CONFIG.topics;
+    let broker = Arc::new(InMemoryBroker::new());
+    for entity in [driver("+33600000000", 1), truck("AB-123-CD", 1)] {
+        let payload = serde_json::to_vec(&entity).unwrap();
+        broker.send(&topics.entity, entity.id(), &payload).unwrap();
+    }
+    broker.send_tombstone(&topics.entity, "D-1").unwrap();
+    let joiner = Mutex::new(Joiner::new());
+    let mut source = broker.subscribe(&[&topics.entity]);
+    assert_eq!(pump(&mut source, broker.as_ref(), &joiner, topics, Duration::ZERO).unwrap(), 3);
+
+    let mut joiner = joiner.into_inner().unwrap();
+    assert_eq!(shift(&mut joiner, 0), Err(Rejection::UnknownDriver("D-1".to_string())));
+}
+
+/// Writes the records as a JSONL file
+fn dump<T: serde::Serialize>(path: &Path, records: &[T]) {
+    let lines: Vec<String> = records.iter().map(|record| serde_json::to_string(record).unwrap()).collect();
+    std::fs::write(path, lines.join("\n")).unwrap();
+}
+
+/// Number of reports of the batch mode on a shift of driver D-1, its dump of the entities
+/// ending with `lines`
+fn batch_reports(name: &str, lines: &[serde_json::Value]) -> usize {
+    let dir = std::env::temp_dir().join(format!("merger-{}-{}", name, std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let mut entities = vec![serde_json::to_value(driver("+33600000000", 1)).unwrap(), serde_json::to_value(truck("AB-123-CD", 1)).unwrap()];
+    entities.extend_from_slice(lines);
+    dump(&dir.join("entities.jsonl"), &entities);
+    let plan = [
+        (TimeRegistrationType::StartDay, 0),
+        (TimeRegistrationType::StartBreak, 240),
+        (TimeRegistrationType::EndBreak, 285),
+        (TimeRegistrationType::EndDay, 480),
+    ];
+    dump(&dir.join("registrations.jsonl"), &plan.map(|(kind, minutes)| registration(kind, minutes)));
+    dump(&dir.join("positions.jsonl"), &plan.map(|(_, minutes)| position(minutes)));
+
+    let input = BatchInput {
+        entities: &dir.join("entities.jsonl"),
+        time_registrations: &dir.join("registrations.jsonl"),
+        positions: &dir.join("positions.jsonl"),
+    };
+    let written = batch(&input, Joiner::new(), &dir.join("reports.jsonl")).unwrap();
+    std::fs::remove_dir_all(dir).unwrap();
+    written
+}
+
+#[test]
+fn the_batch_mode_reads_the_tombstones_of_the_entity_dump() {
+    assert_eq!(batch_reports("alive", &[]), 1);
+    let tombstone = Tombstone {
+        key: "D-1".to_string(),
+        payload: (),
+    };
+    assert_eq!(batch_reports("deleted", &[serde_json::to_value(tombstone).unwrap()]), 0);
+}
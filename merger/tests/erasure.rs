@@ -0,0 +1,69 @@
+mod common;
+
+use common::{joiner, position, registration, PARTITION};
+use kafkamion_broker::{InMemoryBroker, Sink};
+use merger::config::CONFIG;
+use merger::consumer::pump;
+use merger::join::{Joiner, Rejection};
+use merger::models::entity::Entity;
+use merger::models::erasure::ErasureRequest;
+use merger::models::time_registration::TimeRegistrationType;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+fn erasure() -> ErasureRequest {
+    ErasureRequest {
+        driver_id: "D-1".to_string(),
+        requested_at: "2025-01-10T09:00:00+00:00".to_string(),
+    }
+}
+
+/// Registers a position of the truck then a time registration of D-1 `minutes` after `START`
+fn register(joiner: &mut Joiner, kind: TimeRegistrationType, minutes: i64) -> Result<usize, Rejection> {
+    let mut reports = joiner.on_position(PARTITION, position(minutes))?.len();
+    reports += joiner.on_time_registration(PARTITION, registration(kind, minutes))?.len();
+    Ok(reports)
+}
+
+#[test]
+fn the_shift_in_progress_of_an_erased_driver_is_never_reported() {
+    let mut joiner = joiner();
+    register(&mut joiner, TimeRegistrationType::StartDay, 0).unwrap();
+    register(&mut joiner, TimeRegistrationType::StartBreak, 240).unwrap();
+    register(&mut joiner, TimeRegistrationType::EndBreak, 285).unwrap();
+    joiner.on_erasure(erasure());
+
+    let rejected = register(&mut joiner, TimeRegistrationType::EndDay, 480);
+    assert_eq!(rejected, Err(Rejection::ErasedDriver("D-1".to_string())));
+    assert!(!rejected.unwrap_err().is_transient());
+    assert!(joiner.flush().is_empty());
+}
+
+#[test]
+fn an_erased_driver_is_not_stored_again() {
+    let mut joiner = joiner();
+    joiner.on_erasure(erasure());
+    joiner.on_entity(Entity::Driver(common::driver("D-1")));
+    assert_eq!(
+        register(&mut joiner, TimeRegistrationType::StartDay, 0),
+        Err(Rejection::ErasedDriver("D-1".to_string()))
+    );
+}
+
+#[test]
+fn the_erasure_topic_erases_its_drivers() {
+    let topics = &CONFIG.topics;
+    let broker = Arc::new(InMemoryBroker::new());
+    let payload = serde_json::to_vec(&erasure()).unwrap();
+    broker.send(&topics.erasure, "D-1", &payload).unwrap();
+    let joiner = Mutex::new(joiner());
+    let mut source = broker.subscribe(&[&topics.erasure]);
+    assert_eq!(pump(&mut source, broker.as_ref(), &joiner, topics, Duration::ZERO).unwrap(), 1);
+    assert!(broker.records(&topics.dead_letter).is_empty());
+
+    let mut joiner = joiner.into_inner().unwrap();
+    assert_eq!(
+        register(&mut joiner, TimeRegistrationType::StartDay, 0),
+        Err(Rejection::ErasedDriver("D-1".to_string()))
+    );
+}
@@ -0,0 +1,103 @@
+mod common;
+
+use common::PARTITION;
+use merger::geofence::GeofenceRegistry;
+use merger::join::{Joiner, Output};
+use merger::models::entity::Entity;
+use merger::models::geofence::{GeofenceEvent, GeofenceEventType};
+use merger::models::position::Position;
+
+const RUNGIS: (f64, f64) = (48.7590, 2.3520);
+const PARIS: (f64, f64) = (48.8566, 2.3522);
+
+fn position(minutes: i64, (latitude, longitude): (f64, f64)) -> Position {
+    Position {
+        latitude,
+        longitude,
+        ..common::position(minutes)
+    }
+}
+
+fn registry() -> GeofenceRegistry {
+    GeofenceRegistry::load("src/resource/geofences.geojson").unwrap()
+}
+
+/// Feeds the positions to a joiner following the geofences of the configuration and returns its
+/// geofence events
+fn replay(positions: impl IntoIterator<Item = Position>) -> Vec<GeofenceEvent> {
+    let mut joiner = Joiner::new().with_geofences(registry());
+    joiner.on_entity(Entity::Truck(common::truck("T-1")));
+    for position in positions {
+        joiner.on_position(PARTITION, position).unwrap();
+    }
+    joiner
+        .take_outputs()
+        .into_iter()
+        .filter_map(|output| match output {
+            Output::Geofence(event) => Some(event),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn positions_are_located_in_circles_and_polygons() {
+    let registry = registry();
+    let ids = |coordinates| -> Vec<String> {
+        let position = position(0, coordinates);
+        registry.containing(&position).map(|geofence| geofence.id.clone()).collect()
+    };
+    assert_eq!(ids(RUNGIS), ["depot-rungis"]);
+    // 1.5 km north of the depot, inside its 2 km radius
+    assert_eq!(ids((RUNGIS.0 + 0.0135, RUNGIS.1)), ["depot-rungis"]);
+    assert!(ids((RUNGIS.0 + 0.03, RUNGIS.1)).is_empty());
+    assert_eq!(ids(PARIS), ["site-paris"]);
+    assert!(ids((45.0, 1.0)).is_empty());
+}
+
+#[test]
+fn a_circle_needs_a_radius() {
+    let geojson = r#"{"type":"FeatureCollection","features":[
+        {"type":"Feature","properties":{"id":"depot"},"geometry":{"type":"Point","coordinates":[2.35,48.76]}}
+    ]}"#;
+    let err = GeofenceRegistry::from_geojson(geojson).unwrap_err();
+    assert!(err.to_string().contains("radius_m"));
+}
+
+#[test]
+fn a_truck_leaving_a_depot_gives_its_dwell_duration() {
+    let events = replay([
+        position(0, RUNGIS),
+        position(30, RUNGIS),
+        position(45, (48.80, 2.3521)),
+        position(90, PARIS),
+    ]);
+    let summary: Vec<(GeofenceEventType, &str, Option<i64>)> = events
+        .iter()
+        .map(|event| (event.kind, event.geofence_id.as_str(), event.dwell_duration))
+        .collect();
+    assert_eq!(
+        summary,
+        [
+            (GeofenceEventType::Enter, "depot-rungis", None),
+            (GeofenceEventType::Exit, "depot-rungis", Some(45 * 60)),
+            (GeofenceEventType::Enter, "site-paris", None),
+        ]
+    );
+    assert_eq!(events[0].geofence_name, "Rungis depot");
+    assert_eq!(events[1].timestamp, common::timestamp(45));
+}
+
+#[test]
+fn a_position_older_than_the_latest_one_is_not_checked() {
+    let events = replay([position(0, RUNGIS), position(60, PARIS), position(30, RUNGIS)]);
+    let kinds: Vec<(GeofenceEventType, &str)> = events.iter().map(|event| (event.kind, event.geofence_id.as_str())).collect();
+    assert_eq!(
+        kinds,
+        [
+            (GeofenceEventType::Enter, "depot-rungis"),
+            (GeofenceEventType::Exit, "depot-rungis"),
+            (GeofenceEventType::Enter, "site-paris"),
+        ]
+    );
+}
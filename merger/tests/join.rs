@@ -0,0 +1,110 @@
+mod common;
+
+use common::{joiner, position, registration, timestamp, PARTITION};
+use merger::join::{Joiner, Rejection};
+use merger::models::entity::Entity;
+use merger::models::position::Position;
+use merger::models::report::Report;
+use merger::models::time_registration::TimeRegistrationType;
+
+/// Registrations of a shift with one break, in minutes after `START`
+const SHIFT: [(TimeRegistrationType, i64); 4] = [
+    (TimeRegistrationType::StartDay, 0),
+    (TimeRegistrationType::StartBreak, 240),
+    (TimeRegistrationType::EndBreak, 285),
+    (TimeRegistrationType::EndDay, 480),
+];
+
+/// Registers the shift of D-1 on T-1, then the given positions of its truck
+fn replay(joiner: &mut Joiner, positions: impl IntoIterator<Item = Position>) -> Result<Vec<Report>, Rejection> {
+    let mut reports = Vec::new();
+    for (kind, minutes) in SHIFT {
+        reports.extend(joiner.on_time_registration(PARTITION, registration(kind, minutes))?);
+    }
+    for position in positions {
+        reports.extend(joiner.on_position(PARTITION, position)?);
+    }
+    Ok(reports)
+}
+
+/// The positions of the truck at each registration of the shift
+fn exact_positions() -> Vec<Position> {
+    SHIFT.iter().map(|(_, minutes)| position(*minutes)).collect()
+}
+
+#[test]
+fn a_shift_is_reported_with_its_driver_its_truck_and_its_locations() {
+    let reports = replay(&mut joiner(), exact_positions()).unwrap();
+    assert_eq!(reports.len(), 1);
+    let report = &reports[0];
+    assert_eq!((report.driver_id.as_str(), report.first_name.as_str(), report.last_name.as_str()), ("D-1", "Jane", "Doe"));
+    assert_eq!((report.email.as_str(), report.phone.as_str()), ("jane.doe@example.com", "+33600000000"));
+    assert_eq!((report.truck_id.as_str(), report.immatriculation.as_str()), ("T-1", "AB-123-CD"));
+    assert_eq!((report.start_time.clone(), report.end_time.clone()), (timestamp(0), timestamp(480)));
+    assert_eq!(report.rest_time, (45 * 60).to_string());
+    assert_eq!((report.latitude_start, report.longitude_start), (position(0).latitude, position(0).longitude));
+    assert_eq!((report.latitude_end, report.timestamp_end.clone()), (position(480).latitude, timestamp(480)));
+    assert_eq!((report.latitude_rest, report.timestamp_rest.clone()), (position(240).latitude, timestamp(240)));
+    assert_eq!((report.offset_start, report.offset_end, report.offset_rest), (0, 0, 0));
+    assert_eq!(report.breaks.len(), 1);
+    assert_eq!((report.breaks[0].start_time.clone(), report.breaks[0].end_time.clone()), (timestamp(240), timestamp(285)));
+    assert_eq!(report.breaks[0].duration, 45 * 60);
+}
+
+#[test]
+fn the_events_of_an_unknown_driver_or_truck_are_rejected() {
+    let mut joiner = Joiner::new();
+    let rejected = replay(&mut joiner, exact_positions());
+    assert_eq!(rejected, Err(Rejection::UnknownDriver("D-1".to_string())));
+    assert!(rejected.unwrap_err().is_transient());
+
+    joiner.on_entity(Entity::Driver(common::driver("D-1")));
+    assert_eq!(replay(&mut joiner, exact_positions()), Err(Rejection::UnknownTruck("T-1".to_string())));
+}
+
+#[test]
+fn a_shift_missing_its_driver_is_reported_once_the_driver_is_known() {
+    let mut joiner = joiner();
+    for (kind, minutes) in SHIFT {
+        joiner.on_time_registration(PARTITION, registration(kind, minutes)).unwrap();
+    }
+    joiner.on_entity_deleted("D-1");
+    for position in exact_positions() {
+        assert!(joiner.on_position(PARTITION, position).unwrap().is_empty());
+    }
+    assert!(joiner.flush().is_empty());
+
+    let reports = joiner.on_entity(Entity::Driver(common::driver("D-1")));
+    assert_eq!(reports.len(), 1);
+    assert_eq!(reports[0].driver_id, "D-1");
+}
+
+#[test]
+fn a_position_beyond_the_tolerance_does_not_locate_the_shift() {
+    // 3 minutes after each registration, the default tolerance being 2 minutes
+    let late_positions = SHIFT.iter().map(|(_, minutes)| position(minutes + 3));
+    let mut unlocated = joiner();
+    assert!(replay(&mut unlocated, late_positions).unwrap().is_empty());
+    assert!(unlocated.flush().is_empty());
+
+    let reports = replay(&mut joiner(), SHIFT.iter().map(|(_, minutes)| position(minutes + 2))).unwrap();
+    assert_eq!(reports.len(), 1);
+    assert_eq!(reports[0].offset_start, 120);
+}
+
+#[test]
+fn a_shift_without_break_is_reported_with_empty_rest_fields() {
+    let mut joiner = joiner();
+    let mut reports = Vec::new();
+    for (kind, minutes) in [(TimeRegistrationType::StartDay, 0), (TimeRegistrationType::EndDay, 300)] {
+        reports.extend(joiner.on_position(PARTITION, position(minutes)).unwrap());
+        reports.extend(joiner.on_time_registration(PARTITION, registration(kind, minutes)).unwrap());
+    }
+    assert_eq!(reports.len(), 1);
+    let report = &reports[0];
+    assert!(report.breaks.is_empty());
+    assert_eq!((report.rest_time.as_str(), report.timestamp_rest.as_str()), ("0", ""));
+    assert_eq!((report.latitude_rest, report.longitude_rest, report.offset_rest), (0.0, 0.0, 0));
+    assert_eq!(report.shift_duration, 300 * 60);
+    assert_eq!(report.break_duration, 0);
+}
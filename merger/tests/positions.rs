@@ -0,0 +1,117 @@
+mod common;
+
+use chrono::Duration;
+use common::{PARTITION, START};
+use merger::config::PositionMatching;
+use merger::join::Joiner;
+use merger::models::position::Position;
+use merger::models::report::Report;
+use merger::models::time_registration::{TimeRegistration, TimeRegistrationType};
+
+const SECOND: i64 = 1_000_000;
+
+/// Registrations of a shift with one break, in seconds after `START`
+const SHIFT: [(TimeRegistrationType, i64); 4] = [
+    (TimeRegistrationType::StartDay, 0),
+    (TimeRegistrationType::StartBreak, 4 * 3600),
+    (TimeRegistrationType::EndBreak, 4 * 3600 + 2700),
+    (TimeRegistrationType::EndDay, 9 * 3600),
+];
+
+fn joiner(matching: PositionMatching) -> Joiner {
+    common::crew(Joiner::new().with_position_matching(matching, Duration::minutes(2)))
+}
+
+/// A position `seconds` after `START`, its latitude growing by 0.001 a second
+fn position(seconds: i64) -> Position {
+    Position {
+        truck_id: "T-1".to_string(),
+        latitude: 45.0 + seconds as f64 / 1000.0,
+        longitude: 2.0,
+        timestamp: (START + seconds * SECOND).to_string(),
+    }
+}
+
+/// Registers the shift, then a position at each of the given offsets in seconds around each of
+/// its registrations. Returns the reports.
+fn replay(joiner: &mut Joiner, offsets: &[i64]) -> Vec<Report> {
+    for (kind, seconds) in SHIFT {
+        let registration = TimeRegistration {
+            timestamp: (START + seconds * SECOND).to_string(),
+            ..common::registration(kind, 0)
+        };
+        joiner.on_time_registration(PARTITION, registration).unwrap();
+    }
+    let mut reports = Vec::new();
+    for (_, seconds) in SHIFT {
+        for offset in offsets {
+            reports.extend(joiner.on_position("input-1", position(seconds + offset)).unwrap());
+        }
+    }
+    reports
+}
+
+#[test]
+fn an_exact_position_has_no_offset() {
+    let reports = replay(&mut joiner(PositionMatching::Interpolate), &[0]);
+    assert_eq!(reports.len(), 1);
+    let report = &reports[0];
+    assert_eq!((report.offset_start, report.interpolated_start), (0, false));
+    assert_eq!(report.timestamp_start, report.start_time);
+}
+
+#[test]
+fn the_nearest_position_locates_the_registration() {
+    let reports = replay(&mut joiner(PositionMatching::Nearest), &[-30, 90]);
+    assert_eq!(reports.len(), 1);
+    let report = &reports[0];
+    assert_eq!((report.offset_start, report.interpolated_start), (-30, false));
+    assert_eq!(report.latitude_start, position(-30).latitude);
+    assert_eq!(report.timestamp_start, position(-30).timestamp);
+    assert_eq!((report.offset_end, report.offset_rest), (-30, -30));
+    assert_eq!((report.breaks[0].offset, report.breaks[0].interpolated), (-30, false));
+}
+
+#[test]
+fn the_location_is_interpolated_between_the_positions_around() {
+    let reports = replay(&mut joiner(PositionMatching::Interpolate), &[-30, 90]);
+    assert_eq!(reports.len(), 1);
+    let report = &reports[0];
+    assert_eq!((report.offset_start, report.interpolated_start), (-30, true));
+    assert!((report.latitude_start - 45.0).abs() < 1e-9);
+    assert_eq!(report.timestamp_start, report.start_time);
+    assert!(report.breaks[0].interpolated);
+}
+
+#[test]
+fn a_position_beyond_the_tolerance_is_ignored() {
+    // the position after is too far to interpolate with
+    let reports = replay(&mut joiner(PositionMatching::Interpolate), &[-30, 150]);
+    assert_eq!(reports.len(), 1);
+    assert_eq!((reports[0].offset_end, reports[0].interpolated_end), (-30, false));
+
+    let mut unlocated = joiner(PositionMatching::Nearest);
+    assert!(replay(&mut unlocated, &[-150, 150]).is_empty());
+    assert!(unlocated.flush().is_empty());
+}
+
+#[test]
+fn the_location_waits_for_a_later_position() {
+    let mut joiner = joiner(PositionMatching::Nearest);
+    // the positions before the end_day are not enough, a nearer one may follow
+    let reports = replay(&mut joiner, &[-30]);
+    assert!(reports.is_empty());
+
+    let reports = joiner.on_position("input-1", position(9 * 3600 + 600)).unwrap();
+    assert_eq!(reports.len(), 1);
+    assert_eq!(reports[0].offset_end, -30);
+}
+
+#[test]
+fn flush_locates_with_the_positions_received() {
+    let mut joiner = joiner(PositionMatching::Nearest);
+    assert!(replay(&mut joiner, &[-30]).is_empty());
+    let reports = joiner.flush();
+    assert_eq!(reports.len(), 1);
+    assert_eq!(reports[0].offset_end, -30);
+}
@@ -0,0 +1,68 @@
+mod common;
+
+use kafkamion_broker::{BrokerError, InMemoryBroker, Sink};
+use merger::config::CONFIG;
+use merger::consumer::pump;
+use merger::join::Joiner;
+use merger::models::entity::Entity;
+use merger::state::{ChangelogStore, InMemoryStore};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Broker failing every send to one topic, as a full producer queue would
+struct FailingSink {
+    broker: Arc<InMemoryBroker>,
+    topic: String,
+}
+
+impl Sink for FailingSink {
+    fn send_with_headers(&self, topic: &str, key: &str, payload: &[u8], headers: &[(&str, &str)]) -> Result<(), BrokerError> {
+        if topic == self.topic {
+            return Err(BrokerError("queue full".to_string()));
+        }
+        self.broker.send_with_headers(topic, key, payload, headers)
+    }
+
+    fn send_tombstone(&self, topic: &str, key: &str) -> Result<(), BrokerError> {
+        if topic == self.topic {
+            return Err(BrokerError("queue full".to_string()));
+        }
+        self.broker.send_tombstone(topic, key)
+    }
+}
+
+fn failing(broker: &Arc<InMemoryBroker>, topic: &str) -> FailingSink {
+    FailingSink {
+        broker: Arc::clone(broker),
+        topic: topic.to_string(),
+    }
+}
+
+#[test]
+fn a_record_that_cannot_be_sent_stops_the_pump() {
+    let topics = &CONFIG.topics;
+    let broker = Arc::new(InMemoryBroker::new());
+    broker.send(&topics.position, "T-1", b"not a position").unwrap();
+    broker.send(&topics.position, "T-1", b"nor this one").unwrap();
+    let joiner = Mutex::new(common::joiner());
+    let mut source = broker.subscribe(&[&topics.position]);
+
+    let sink = failing(&broker, &topics.dead_letter);
+    assert!(pump(&mut source, &sink, &joiner, topics, Duration::ZERO).is_err());
+    // the second record is left to the transaction joining it again
+    assert_eq!(pump(&mut source, broker.as_ref(), &joiner, topics, Duration::ZERO).unwrap(), 1);
+}
+
+#[test]
+fn a_change_of_the_state_that_cannot_be_logged_stops_the_pump() {
+    let topics = &CONFIG.topics;
+    let broker = Arc::new(InMemoryBroker::new());
+    let payload = serde_json::to_vec(&Entity::Driver(common::driver("D-1"))).unwrap();
+    broker.send(&topics.entity, "D-1", &payload).unwrap();
+    let store = ChangelogStore::new(Box::new(InMemoryStore::new()), Arc::new(failing(&broker, &topics.changelog)), &topics.changelog);
+    let joiner = Mutex::new(Joiner::with_store(Box::new(store)));
+    let mut source = broker.subscribe(&[&topics.entity]);
+
+    assert!(pump(&mut source, broker.as_ref(), &joiner, topics, Duration::ZERO).is_err());
+    assert!(joiner.lock().unwrap().take_failure().is_none());
+}
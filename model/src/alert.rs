@@ -0,0 +1,24 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertType {
+    /// Two drivers on shift on the same truck at the same time
+    DoubleBookedTruck,
+    /// A driver on shift on two trucks at the same time
+    DriverOnTwoTrucks,
+    /// A truck moving while no driver is on shift on it
+    TruckMovingWithoutDriver,
+}
+
+/// A record of the `alerts_topic`, a conflict in the assignments of the drivers to the trucks
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Alert {
+    #[serde(rename = "type")]
+    pub kind: AlertType,
+    /// Trucks and drivers in conflict
+    pub truck_ids: Vec<String>,
+    pub driver_ids: Vec<String>,
+    /// Timestamp of the registration or the position revealing the conflict
+    pub timestamp: String,
+}
@@ -0,0 +1,24 @@
+use crate::time_registration::TimeRegistration;
+use serde::{Deserialize, Serialize};
+
+/// Duty status of a driver, as given by the sequence of its time registrations
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum DutyStatus {
+    /// Before a `start_day` or after an `end_day`
+    OffDuty,
+    /// Between a `start_day` and an `end_day`, outside of the breaks
+    OnShift,
+    /// Between a `start_break` and an `end_break`
+    OnBreak,
+}
+
+/// A record of the `anomalies_topic`, a time registration that cannot follow the previous ones
+/// of its driver and was left out of the reports
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Anomaly {
+    pub registration: TimeRegistration,
+    /// Status of the driver when the registration happened
+    pub status: DutyStatus,
+    pub reason: String,
+}
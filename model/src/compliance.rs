@@ -0,0 +1,30 @@
+use serde::{Deserialize, Serialize};
+
+/// Driving hours rules of the Regulation (EC) No 561/2006
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum Rule {
+    /// A break of 45 minutes, or of 15 then 30 minutes, after 4.5 hours of driving
+    BreakAfterDriving,
+    /// 9 hours of driving a day, 10 hours twice a week
+    DailyDriving,
+    /// 11 hours of rest between two days
+    DailyRest,
+    /// 56 hours of driving a week
+    WeeklyDriving,
+    /// 90 hours of driving in two consecutive weeks
+    FortnightlyDriving,
+}
+
+/// A record of the `compliance_topic`, a breach of a driving hours rule by a driver
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Violation {
+    pub driver_id: String,
+    pub rule: Rule,
+    /// Start and end of the period the rule was breached on
+    pub period_start: String,
+    pub period_end: String,
+    /// Limit of the rule and duration measured on the period, in seconds
+    pub limit: i64,
+    pub actual: i64,
+}
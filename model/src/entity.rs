@@ -0,0 +1,56 @@
+use serde::{Deserialize, Serialize};
+
+/// A record of the `entity_topic`, tagged by its `type` field and keyed by the `driver_id` or
+/// the `truck_id`. A later record of the same key updates the entity, a tombstone deletes it.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Entity {
+    Driver(Driver),
+    Truck(Truck),
+}
+
+impl Entity {
+    /// Key of the entity in the `entity_topic`
+    pub fn id(&self) -> &str {
+        match self {
+            Entity::Driver(driver) => &driver.driver_id,
+            Entity::Truck(truck) => &truck.truck_id,
+        }
+    }
+
+    pub fn version(&self) -> u64 {
+        match self {
+            Entity::Driver(driver) => driver.version,
+            Entity::Truck(truck) => truck.version,
+        }
+    }
+}
+
+/// A tombstone of the `entity_topic` in a JSONL dump, `{"key": "<id>", "payload": null}`, the
+/// lines of the entities having no key of their own
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Tombstone {
+    pub key: String,
+    pub payload: (),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Driver {
+    pub driver_id: String,
+    pub first_name: String,
+    pub last_name: String,
+    pub email: String,
+    pub phone: String,
+    /// Incremented by each update of the driver, 0 for the records without version
+    #[serde(default)]
+    pub version: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Truck {
+    pub truck_id: String,
+    pub immatriculation: String,
+    /// Incremented by each update of the truck, 0 for the records without version
+    #[serde(default)]
+    pub version: u64,
+}
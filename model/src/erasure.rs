@@ -0,0 +1,40 @@
+use serde::{Deserialize, Serialize};
+
+/// Fields of a report holding the personal data of its driver
+pub const PERSONAL_FIELDS: [&str; 4] = ["first_name", "last_name", "email", "phone"];
+
+/// A record of the `erasure_topic`, keyed by `driver_id`, asking to erase the personal data of a
+/// driver under the right to erasure
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ErasureRequest {
+    pub driver_id: String,
+    pub requested_at: String,
+}
+
+/// What was erased for a driver from the stored reports
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ErasureAudit {
+    pub driver_id: String,
+    pub erased_at: String,
+    /// The personal fields emptied in the reports of the driver
+    pub fields: Vec<String>,
+    /// The objects rewritten, those without report of the driver being left untouched
+    pub objects: Vec<ErasedObject>,
+    /// The objects that could not be erased, left as they were
+    #[serde(default)]
+    pub failures: Vec<FailedObject>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ErasedObject {
+    pub key: String,
+    /// Number of reports of the driver erased in the object
+    pub rows: usize,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct FailedObject {
+    pub key: String,
+    /// Why the object could not be read, rewritten or uploaded
+    pub reason: String,
+}
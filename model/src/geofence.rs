@@ -0,0 +1,25 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum GeofenceEventType {
+    Enter,
+    Exit,
+}
+
+/// A record of the `geofence_topic`, a truck entering or leaving a depot or a customer site
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct GeofenceEvent {
+    #[serde(rename = "type")]
+    pub kind: GeofenceEventType,
+    pub truck_id: String,
+    pub geofence_id: String,
+    pub geofence_name: String,
+    /// Timestamp and coordinates of the first position of the truck inside the geofence on
+    /// enter, outside of it on exit
+    pub timestamp: String,
+    pub latitude: f64,
+    pub longitude: f64,
+    /// Seconds the truck stayed inside the geofence, on exit only
+    pub dwell_duration: Option<i64>,
+}
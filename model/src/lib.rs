@@ -0,0 +1,19 @@
+//! Wire types exchanged on the Kafka topics of the pipeline.
+//!
+//! The producer writes `Entity`, `TimeRegistration` and `Position` records, the merger joins
+//! them into `Report` records and the saver stores those reports. The merger also checks the
+//! time registrations against the driving hours rules and publishes the `Violation` records,
+//! and the `Anomaly` records of the registrations out of sequence, and follows the trucks in
+//! and out of the geofences with the `GeofenceEvent` records. The `Alert` records report the
+//! conflicts in the assignments of the drivers to the trucks. An `ErasureRequest` erases the
+//! personal data of a driver, the saver recording what it erased in an `ErasureAudit`.
+
+pub mod alert;
+pub mod anomaly;
+pub mod compliance;
+pub mod entity;
+pub mod erasure;
+pub mod geofence;
+pub mod position;
+pub mod report;
+pub mod time_registration;
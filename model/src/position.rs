@@ -0,0 +1,10 @@
+use serde::{Deserialize, Serialize};
+
+/// A record of the `position_topic`
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Position {
+    pub truck_id: String,
+    pub latitude: f64,
+    pub longitude: f64,
+    pub timestamp: String,
+}
@@ -0,0 +1,82 @@
+use serde::{Deserialize, Serialize};
+
+/// A break of a shift, located where the truck stopped
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Break {
+    pub start_time: String,
+    pub end_time: String,
+    /// Duration of the break in seconds
+    pub duration: i64,
+    pub latitude: f64,
+    pub longitude: f64,
+    /// Seconds from `start_time` to the position locating the break, negative when it is earlier
+    #[serde(default)]
+    pub offset: i64,
+    /// Whether the location is interpolated between the positions around `start_time`
+    #[serde(default)]
+    pub interpolated: bool,
+}
+
+/// A record of the `report_topic`, the join of the three input topics.
+///
+/// The `rest` fields summarize the breaks for the flat consumers: `rest_time` is their total
+/// duration in seconds and the location is the one of the first break.
+///
+/// Each location is taken from the position of the truck nearest in time to its registration,
+/// or interpolated between the positions around it. The `offset` fields give the seconds from
+/// the registration to that nearest position, negative when it is earlier.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Report {
+    pub driver_id: String,
+    pub first_name: String,
+    pub last_name: String,
+    pub email: String,
+    pub phone: String,
+    pub truck_id: String,
+    pub immatriculation: String,
+    pub start_time: String,
+    pub end_time: String,
+    pub rest_time: String,
+    pub latitude_start: f64,
+    pub longitude_start: f64,
+    pub timestamp_start: String,
+    pub latitude_end: f64,
+    pub longitude_end: f64,
+    pub timestamp_end: String,
+    pub latitude_rest: f64,
+    pub longitude_rest: f64,
+    pub timestamp_rest: String,
+    /// Seconds from `start_time` to `end_time`
+    #[serde(default)]
+    pub shift_duration: i64,
+    /// Seconds of the shift outside of the breaks
+    #[serde(default)]
+    pub driving_duration: i64,
+    /// Seconds of the breaks, the same as `rest_time`
+    #[serde(default)]
+    pub break_duration: i64,
+    /// Distance travelled by the truck during the shift, summed between its consecutive positions
+    #[serde(default)]
+    pub distance_km: f64,
+    /// Distance over the driving duration
+    #[serde(default)]
+    pub average_speed_kmh: f64,
+    /// Greatest speed between two consecutive positions
+    #[serde(default)]
+    pub max_speed_kmh: f64,
+    #[serde(default)]
+    pub offset_start: i64,
+    #[serde(default)]
+    pub interpolated_start: bool,
+    #[serde(default)]
+    pub offset_end: i64,
+    #[serde(default)]
+    pub interpolated_end: bool,
+    #[serde(default)]
+    pub offset_rest: i64,
+    #[serde(default)]
+    pub interpolated_rest: bool,
+    /// Every break of the shift, in time order
+    #[serde(default)]
+    pub breaks: Vec<Break>,
+}
@@ -0,0 +1,38 @@
+use serde::{Deserialize, Serialize};
+use std::fmt::{self, Display};
+
+/// A record of the `time_registration_topic`
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TimeRegistration {
+    #[serde(rename = "type")]
+    pub kind: TimeRegistrationType,
+    pub timestamp: String,
+    pub driver_id: String,
+    pub truck_id: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum TimeRegistrationType {
+    StartDay,
+    StartBreak,
+    EndBreak,
+    EndDay,
+}
+
+impl TimeRegistrationType {
+    pub fn associated_string(&self) -> String {
+        match self {
+            Self::StartDay => "start_day".to_string(),
+            Self::StartBreak => "start_break".to_string(),
+            Self::EndBreak => "end_break".to_string(),
+            Self::EndDay => "end_day".to_string(),
+        }
+    }
+}
+
+impl Display for TimeRegistrationType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.associated_string())
+    }
+}
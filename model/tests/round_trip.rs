@@ -0,0 +1,387 @@
+use kafkamion_model::alert::{Alert, AlertType};
+use kafkamion_model::anomaly::{Anomaly, DutyStatus};
+use kafkamion_model::compliance::{Rule, Violation};
+use kafkamion_model::entity::{Driver, Entity, Truck};
+use kafkamion_model::erasure::{ErasedObject, ErasureAudit, FailedObject, PERSONAL_FIELDS};
+use kafkamion_model::geofence::{GeofenceEvent, GeofenceEventType};
+use kafkamion_model::position::Position;
+use kafkamion_model::report::{Break, Report};
+use kafkamion_model::time_registration::{TimeRegistration, TimeRegistrationType};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{json, Value};
+
+/// Serializes `value`, checks it against the expected JSON and deserializes it back
+fn assert_round_trip<T>(value: &T, expected: Value)
+where
+    T: Serialize + DeserializeOwned + PartialEq + std::fmt::Debug,
+{
+    let serialized = serde_json::to_value(value).unwrap();
+    assert_eq!(serialized, expected);
+    let deserialized: T = serde_json::from_value(serialized).unwrap();
+    assert_eq!(&deserialized, value);
+}
+
+#[test]
+fn driver_entity_is_tagged_by_type() {
+    let entity = Entity::Driver(Driver {
+        driver_id: "D-123".to_string(),
+        first_name: "Baptiste".to_string(),
+        last_name: "Bronsin".to_string(),
+        email: "baptiste@example.com".to_string(),
+        phone: "+33650353421".to_string(),
+        version: 2,
+    });
+    assert_round_trip(
+        &entity,
+        json!({
+            "type": "driver",
+            "driver_id": "D-123",
+            "first_name": "Baptiste",
+            "last_name": "Bronsin",
+            "email": "baptiste@example.com",
+            "phone": "+33650353421",
+            "version": 2
+        }),
+    );
+}
+
+#[test]
+fn truck_entity_is_tagged_by_type() {
+    let entity = Entity::Truck(Truck {
+        truck_id: "T-123".to_string(),
+        immatriculation: "AB-123-CD".to_string(),
+        version: 1,
+    });
+    assert_round_trip(
+        &entity,
+        json!({
+            "type": "truck",
+            "truck_id": "T-123",
+            "immatriculation": "AB-123-CD",
+            "version": 1
+        }),
+    );
+}
+
+#[test]
+fn entity_without_version_is_version_0() {
+    let entity: Entity = serde_json::from_value(json!({
+        "type": "truck",
+        "truck_id": "T-123",
+        "immatriculation": "AB-123-CD"
+    }))
+    .unwrap();
+    assert_eq!(entity.id(), "T-123");
+    assert_eq!(entity.version(), 0);
+}
+
+#[test]
+fn violation_names_its_rule_in_snake_case() {
+    let violation = Violation {
+        driver_id: "D-123".to_string(),
+        rule: Rule::BreakAfterDriving,
+        period_start: "1736402400000000".to_string(),
+        period_end: "1736420400000000".to_string(),
+        limit: 16200,
+        actual: 18000,
+    };
+    assert_round_trip(
+        &violation,
+        json!({
+            "driver_id": "D-123",
+            "rule": "break_after_driving",
+            "period_start": "1736402400000000",
+            "period_end": "1736420400000000",
+            "limit": 16200,
+            "actual": 18000
+        }),
+    );
+}
+
+#[test]
+fn anomaly_nests_its_time_registration() {
+    let anomaly = Anomaly {
+        registration: TimeRegistration {
+            kind: TimeRegistrationType::EndBreak,
+            timestamp: "1736412060000000".to_string(),
+            driver_id: "D-123".to_string(),
+            truck_id: "T-123".to_string(),
+        },
+        status: DutyStatus::OnShift,
+        reason: "end_break without start_break".to_string(),
+    };
+    assert_round_trip(
+        &anomaly,
+        json!({
+            "registration": {
+                "type": "end_break",
+                "timestamp": "1736412060000000",
+                "driver_id": "D-123",
+                "truck_id": "T-123"
+            },
+            "status": "on_shift",
+            "reason": "end_break without start_break"
+        }),
+    );
+}
+
+#[test]
+fn geofence_event_gives_the_dwell_duration_on_exit_only() {
+    let enter = GeofenceEvent {
+        kind: GeofenceEventType::Enter,
+        truck_id: "T-123".to_string(),
+        geofence_id: "depot-rungis".to_string(),
+        geofence_name: "Rungis depot".to_string(),
+        timestamp: "1736412060000000".to_string(),
+        latitude: 48.759,
+        longitude: 2.352,
+        dwell_duration: None,
+    };
+    assert_round_trip(
+        &enter,
+        json!({
+            "type": "enter",
+            "truck_id": "T-123",
+            "geofence_id": "depot-rungis",
+            "geofence_name": "Rungis depot",
+            "timestamp": "1736412060000000",
+            "latitude": 48.759,
+            "longitude": 2.352,
+            "dwell_duration": null
+        }),
+    );
+    let exit = GeofenceEvent {
+        kind: GeofenceEventType::Exit,
+        timestamp: "1736415660000000".to_string(),
+        dwell_duration: Some(3600),
+        ..enter
+    };
+    let value = serde_json::to_value(&exit).unwrap();
+    assert_eq!((&value["type"], &value["dwell_duration"]), (&json!("exit"), &json!(3600)));
+}
+
+#[test]
+fn alert_names_its_type_in_snake_case() {
+    let alert = Alert {
+        kind: AlertType::DoubleBookedTruck,
+        truck_ids: vec!["T-123".to_string()],
+        driver_ids: vec!["D-123".to_string(), "D-456".to_string()],
+        timestamp: "1736412060000000".to_string(),
+    };
+    assert_round_trip(
+        &alert,
+        json!({
+            "type": "double_booked_truck",
+            "truck_ids": ["T-123"],
+            "driver_ids": ["D-123", "D-456"],
+            "timestamp": "1736412060000000"
+        }),
+    );
+}
+
+#[test]
+fn erasure_audit_lists_the_objects_rewritten() {
+    let audit = ErasureAudit {
+        driver_id: "D-123".to_string(),
+        erased_at: "2025-01-10T09:00:00+00:00".to_string(),
+        fields: PERSONAL_FIELDS.iter().map(|field| field.to_string()).collect(),
+        objects: vec![ErasedObject {
+            key: "kafkamion/reports/report_topic-0-42.parquet".to_string(),
+            rows: 3,
+        }],
+        failures: vec![FailedObject {
+            key: "kafkamion/uploads/broken.parquet".to_string(),
+            reason: "no driver_id column".to_string(),
+        }],
+    };
+    assert_round_trip(
+        &audit,
+        json!({
+            "driver_id": "D-123",
+            "erased_at": "2025-01-10T09:00:00+00:00",
+            "fields": ["first_name", "last_name", "email", "phone"],
+            "objects": [{"key": "kafkamion/reports/report_topic-0-42.parquet", "rows": 3}],
+            "failures": [{"key": "kafkamion/uploads/broken.parquet", "reason": "no driver_id column"}]
+        }),
+    );
+}
+
+#[test]
+fn unknown_entity_type_is_rejected() {
+    let payload = r#"{"type":"trailer","trailer_id":"X-1"}"#;
+    assert!(serde_json::from_str::<Entity>(payload).is_err());
+}
+
+#[test]
+fn time_registration_uses_snake_case_type() {
+    let kinds = [
+        (TimeRegistrationType::StartDay, "start_day"),
+        (TimeRegistrationType::StartBreak, "start_break"),
+        (TimeRegistrationType::EndBreak, "end_break"),
+        (TimeRegistrationType::EndDay, "end_day"),
+    ];
+    for (kind, name) in kinds {
+        let registration = TimeRegistration {
+            kind,
+            timestamp: "1736412060000000".to_string(),
+            driver_id: "D-123".to_string(),
+            truck_id: "T-123".to_string(),
+        };
+        assert_eq!(kind.to_string(), name);
+        assert_round_trip(
+            &registration,
+            json!({
+                "type": name,
+                "timestamp": "1736412060000000",
+                "driver_id": "D-123",
+                "truck_id": "T-123"
+            }),
+        );
+    }
+}
+
+#[test]
+fn position_coordinates_are_numbers() {
+    let position = Position {
+        truck_id: "T-123".to_string(),
+        latitude: 48.8566,
+        longitude: 2.3522,
+        timestamp: "1736412060000000".to_string(),
+    };
+    assert_round_trip(
+        &position,
+        json!({
+            "truck_id": "T-123",
+            "latitude": 48.8566,
+            "longitude": 2.3522,
+            "timestamp": "1736412060000000"
+        }),
+    );
+    let payload = r#"{"truck_id":"T-123","latitude":"48.8566","longitude":"2.3522","timestamp":"0"}"#;
+    assert!(serde_json::from_str::<Position>(payload).is_err());
+}
+
+fn report() -> Report {
+    Report {
+        driver_id: "D-123".to_string(),
+        first_name: "Baptiste".to_string(),
+        last_name: "Bronsin".to_string(),
+        email: "baptiste@example.com".to_string(),
+        phone: "+33650353421".to_string(),
+        truck_id: "T-123".to_string(),
+        immatriculation: "AB-123-CD".to_string(),
+        start_time: "1736402400000000".to_string(),
+        end_time: "1736438400000000".to_string(),
+        rest_time: "2700".to_string(),
+        latitude_start: 48.8566,
+        longitude_start: 2.3522,
+        timestamp_start: "1736402400000000".to_string(),
+        latitude_end: 45.764,
+        longitude_end: 4.8357,
+        timestamp_end: "1736438400000000".to_string(),
+        latitude_rest: 47.322,
+        longitude_rest: 5.0415,
+        timestamp_rest: "1736416800000000".to_string(),
+        shift_duration: 36000,
+        driving_duration: 33300,
+        break_duration: 2700,
+        distance_km: 412.5,
+        average_speed_kmh: 44.59,
+        max_speed_kmh: 88.2,
+        offset_start: 0,
+        interpolated_start: false,
+        offset_end: -45,
+        interpolated_end: false,
+        offset_rest: 30,
+        interpolated_rest: true,
+        breaks: vec![
+            Break {
+                start_time: "1736416800000000".to_string(),
+                end_time: "1736417700000000".to_string(),
+                duration: 900,
+                latitude: 47.322,
+                longitude: 5.0415,
+                offset: 30,
+                interpolated: true,
+            },
+            Break {
+                start_time: "1736424000000000".to_string(),
+                end_time: "1736425800000000".to_string(),
+                duration: 1800,
+                latitude: 46.7806,
+                longitude: 4.8521,
+                offset: 0,
+                interpolated: false,
+            },
+        ],
+    }
+}
+
+#[test]
+fn report_summarizes_its_breaks() {
+    assert_round_trip(
+        &report(),
+        json!({
+            "driver_id": "D-123",
+            "first_name": "Baptiste",
+            "last_name": "Bronsin",
+            "email": "baptiste@example.com",
+            "phone": "+33650353421",
+            "truck_id": "T-123",
+            "immatriculation": "AB-123-CD",
+            "start_time": "1736402400000000",
+            "end_time": "1736438400000000",
+            "rest_time": "2700",
+            "latitude_start": 48.8566,
+            "longitude_start": 2.3522,
+            "timestamp_start": "1736402400000000",
+            "latitude_end": 45.764,
+            "longitude_end": 4.8357,
+            "timestamp_end": "1736438400000000",
+            "latitude_rest": 47.322,
+            "longitude_rest": 5.0415,
+            "timestamp_rest": "1736416800000000",
+            "shift_duration": 36000,
+            "driving_duration": 33300,
+            "break_duration": 2700,
+            "distance_km": 412.5,
+            "average_speed_kmh": 44.59,
+            "max_speed_kmh": 88.2,
+            "offset_start": 0,
+            "interpolated_start": false,
+            "offset_end": -45,
+            "interpolated_end": false,
+            "offset_rest": 30,
+            "interpolated_rest": true,
+            "breaks": [
+                {
+                    "start_time": "1736416800000000",
+                    "end_time": "1736417700000000",
+                    "duration": 900,
+                    "latitude": 47.322,
+                    "longitude": 5.0415,
+                    "offset": 30,
+                    "interpolated": true
+                },
+                {
+                    "start_time": "1736424000000000",
+                    "end_time": "1736425800000000",
+                    "duration": 1800,
+                    "latitude": 46.7806,
+                    "longitude": 4.8521,
+                    "offset": 0,
+                    "interpolated": false
+                }
+            ]
+        }),
+    );
+}
+
+#[test]
+fn report_without_breaks_is_read() {
+    let mut value = serde_json::to_value(report()).unwrap();
+    value.as_object_mut().unwrap().remove("breaks");
+    let report: Report = serde_json::from_value(value).unwrap();
+    assert!(report.breaks.is_empty());
+}
@@ -1,14 +1,17 @@
-use std::{process::ExitCode, sync::Arc, time::Duration};
+use std::{collections::VecDeque, process::ExitCode, sync::Arc, time::Duration};
 
-use clap::{Parser, Subcommand};
-use tracing::info;
+use clap::{Parser, Subcommand, ValueEnum};
+use kafkamion_model::entity::Entity;
+use tracing::{error, info};
 
 use crate::{
     events::{
-        driver::DriverEvent, generate_and_publish, generate_and_publish_as_group,
-        position::PositionEvent, time_registration::TimeRegistrationEvent, truck::TruckEvent,
+        driver::DriverEvent, fleet::Fleet, generate_and_publish, generate_and_publish_as_group,
+        position::PositionEvent, publish_all, rng::SeededRng, time_registration::{ShiftConfig, TimeRegistrationEvent}, truck::TruckEvent,
+        update::EntityUpdate,
     },
     kafka::KafkaClient,
+    sink::{file::FileSink, stdout::StdoutSink, EventSink},
 };
 
 #[derive(Debug, Parser)]
@@ -23,20 +26,59 @@ pub struct App {
     #[clap(short, long, default_value_t = 30)]
     pub duration: u64,
 
+    /// Number of events per second published by the `run` command
+    #[clap(short, long, default_value_t = 10.0)]
+    pub rate: f64,
+
+    #[clap(flatten)]
+    pub shift: ShiftConfig,
+
+    /// Seed of the generators, the same seed producing the same events
+    #[clap(long)]
+    pub seed: Option<u64>,
+
+    /// Seconds between two positions of a truck whose driver is on duty
+    #[clap(long, default_value_t = 60)]
+    pub position_interval: i64,
+
+    /// Probability for an entity of the `update` command to be deleted after its update
+    #[clap(long, default_value_t = 0.2)]
+    pub deletion_ratio: f64,
+
+    /// Where the events are published
+    #[clap(long, value_enum, default_value_t = SinkKind::Kafka)]
+    pub sink: SinkKind,
+
+    /// Directory of the JSONL files of the `file` sink, one per topic
+    #[clap(long, default_value = "fixtures")]
+    pub output_dir: String,
+
     #[warn(unused_parens)]
-    #[clap(short, long, default_value_t = ("localhost:29092".to_string()))]
+    #[clap(short, long, default_value_t = "localhost:29092".to_string())]
     pub endpoint: String,
 }
 
+/// Highest `--rate` of the `run` command, in events per second
+const MAX_RATE: f64 = 100_000.0;
+
 #[derive(Debug, Subcommand, PartialEq, Eq, Hash)]
 pub enum Command {
     Driver,
     Truck,
     TimeRegistration,
     Position,
-    Run
+    Run,
+    Update,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum SinkKind {
+    Kafka,
+    File,
+    Stdout,
 }
 
+#[derive(Default)]
 pub struct Cli {}
 
 impl Cli {
@@ -46,15 +88,38 @@ impl Cli {
 
     pub async fn execute(&self, app: App) -> Result<ExitCode, ExitCode> {
         info!("Execution...");
-        let client = Arc::new(KafkaClient::new(&app.endpoint).await.map_err(|_| ExitCode::FAILURE)?);
+        if let Err(reason) = app.shift.validate() {
+            error!("Invalid shift configuration: {}", reason);
+            return Err(ExitCode::FAILURE);
+        }
+        if app.position_interval <= 0 {
+            error!("The position interval must be a positive number of seconds");
+            return Err(ExitCode::FAILURE);
+        }
+        if !(0.0..=1.0).contains(&app.deletion_ratio) {
+            error!("The deletion ratio must be between 0 and 1");
+            return Err(ExitCode::FAILURE);
+        }
+        let sink: Arc<dyn EventSink> = match app.sink {
+            SinkKind::Kafka => {
+                let client = KafkaClient::new(&app.endpoint).await.map_err(|_| ExitCode::FAILURE)?;
+                info!("Connected to consumer at {}", app.endpoint);
+                Arc::new(client)
+            }
+            SinkKind::File => Arc::new(FileSink::new(&app.output_dir).map_err(|e| {
+                error!("Unable to create {}: {}", app.output_dir, e);
+                ExitCode::FAILURE
+            })?),
+            SinkKind::Stdout => Arc::new(StdoutSink::new()),
+        };
 
-        info!("Connected to consumer at {}", app.endpoint);
-        match app.action {
+        let rng = SeededRng::new(app.seed);
+        let result = match app.action {
             Command::Truck => {
-                let event_generator = Arc::new(TruckEvent::new());
+                let event_generator = Arc::new(TruckEvent::new(rng.fork()));
                 generate_and_publish(
                     event_generator,
-                    client.clone(),
+                    sink.clone(),
                     app.count,
                     "entity_topic",
                     "truck",
@@ -63,10 +128,10 @@ impl Cli {
                 Ok(ExitCode::SUCCESS)
             }
             Command::Driver => {
-                let event_generator = Arc::new(DriverEvent::new());
+                let event_generator = Arc::new(DriverEvent::new(rng.fork()));
                 generate_and_publish(
                     event_generator,
-                    client.clone(),
+                    sink.clone(),
                     app.count,
                     "entity_topic",
                     "driver",
@@ -75,40 +140,40 @@ impl Cli {
                 Ok(ExitCode::SUCCESS)
             }
             Command::Position => {
-                let event_generator = Arc::new(DriverEvent::new());
+                let event_generator = Arc::new(DriverEvent::new(rng.fork()));
                 let drivers = generate_and_publish(
                     event_generator,
-                    client.clone(),
+                    sink.clone(),
                     app.count,
                     "entity_topic",
                     "driver",
                 )
                 .await;
                 // same with trucks
-                let event_generator = Arc::new(TruckEvent::new());
+                let event_generator = Arc::new(TruckEvent::new(rng.fork()));
                 let trucks = generate_and_publish(
                     event_generator,
-                    client.clone(),
+                    sink.clone(),
                     app.count,
                     "entity_topic",
                     "truck",
                 )
                 .await;
 
-                let event_generator = Arc::new(TimeRegistrationEvent::new(drivers, trucks));
+                let event_generator = Arc::new(TimeRegistrationEvent::new(drivers, trucks, app.shift.clone(), rng.fork()));
                 let time_registrations = generate_and_publish_as_group(
                     event_generator,
-                    client.clone(),
+                    sink.clone(),
                     app.count,
                     "time_registration_topic",
                     "time_registration",
                 )
                 .await;
 
-                let event_generator = Arc::new(PositionEvent::new(time_registrations));
+                let event_generator = Arc::new(PositionEvent::new(time_registrations, chrono::Duration::seconds(app.position_interval), rng.fork()));
                 generate_and_publish(
                     event_generator,
-                    client,
+                    sink.clone(),
                     app.count,
                     "position_topic",
                     "position",
@@ -118,10 +183,10 @@ impl Cli {
             }
             Command::TimeRegistration => {
                 //generating drivers :
-                let event_generator = Arc::new(DriverEvent::new());
+                let event_generator = Arc::new(DriverEvent::new(rng.fork()));
                 let drivers = generate_and_publish(
                     event_generator,
-                    client.clone(),
+                    sink.clone(),
                     app.count,
                     "entity_topic",
                     "driver",
@@ -129,20 +194,20 @@ impl Cli {
                 .await;
 
                 // same with trucks
-                let event_generator = Arc::new(TruckEvent::new());
+                let event_generator = Arc::new(TruckEvent::new(rng.fork()));
                 let trucks = generate_and_publish(
                     event_generator,
-                    client.clone(),
+                    sink.clone(),
                     app.count,
                     "entity_topic",
                     "truck",
                 )
                 .await;
 
-                let event_generator = Arc::new(TimeRegistrationEvent::new(drivers, trucks));
+                let event_generator = Arc::new(TimeRegistrationEvent::new(drivers, trucks, app.shift.clone(), rng.fork()));
                 generate_and_publish(
                     event_generator,
-                    client,
+                    sink.clone(),
                     app.count,
                     "time_registration_topic",
                     "time_registration",
@@ -150,18 +215,73 @@ impl Cli {
                 .await;
                 Ok(ExitCode::SUCCESS)
             }
-            Command::Run => {
-                let duration = Duration::from_secs(app.duration);
-                let (timeout_tx, mut timeout_rx) = mpsc::channel();
-                let task = tokio::spawn(async {
-                    if let Err(e) = timeout_rx.recv().await {
-                        error!("timeout: {}", e);
-                    }
+            Command::Update => {
+                let event_generator = Arc::new(DriverEvent::new(rng.fork()));
+                let drivers = generate_and_publish(
+                    event_generator,
+                    sink.clone(),
+                    app.count,
+                    "entity_topic",
+                    "driver",
+                )
+                .await;
+                let event_generator = Arc::new(TruckEvent::new(rng.fork()));
+                let trucks = generate_and_publish(
+                    event_generator,
+                    sink.clone(),
+                    app.count,
+                    "entity_topic",
+                    "truck",
+                )
+                .await;
 
-                });
+                let entities = drivers.into_iter().map(Entity::Driver).chain(trucks.into_iter().map(Entity::Truck)).collect();
+                EntityUpdate::new(app.deletion_ratio, rng.fork())
+                    .publish(sink.as_ref(), "entity_topic", entities)
+                    .await;
+                Ok(ExitCode::SUCCESS)
+            }
+            Command::Run => {
+                if !(app.rate > 0.0 && app.rate <= MAX_RATE) {
+                    error!("The rate must be a number of events per second above 0 and up to {}", MAX_RATE);
+                    return Err(ExitCode::FAILURE);
+                }
+                let mut fleet = Fleet::new(rng.fork());
+                // the events of a step are published one per tick, the fleet is stepped again
+                // once they are all out
+                let mut pending: VecDeque<_> = fleet.hire(app.count).into();
 
+                let deadline = tokio::time::sleep(Duration::from_secs(app.duration));
+                let interrupted = tokio::signal::ctrl_c();
+                tokio::pin!(deadline, interrupted);
+                let mut ticker = tokio::time::interval(Duration::from_secs_f64(1.0 / app.rate));
+                info!("Simulating {} crews for {}s at {} events/s", app.count, app.duration, app.rate);
+                loop {
+                    tokio::select! {
+                        _ = ticker.tick() => {
+                            if pending.is_empty() {
+                                pending.extend(fleet.step());
+                            }
+                            if let Some(message) = pending.pop_front() {
+                                sink.publish(message.topic, &message.payload, &message.key).await;
+                            }
+                        }
+                        _ = &mut deadline => {
+                            info!("Simulation over");
+                            break;
+                        }
+                        _ = &mut interrupted => {
+                            info!("Interrupted, ending the days in progress");
+                            break;
+                        }
+                    }
+                }
+                pending.extend(fleet.finish());
+                publish_all(sink.as_ref(), pending.into()).await;
                 Ok(ExitCode::SUCCESS)
             }
-        }
+        };
+        sink.flush();
+        result
     }
 }
@@ -1,34 +1,21 @@
-use fake::{Dummy, Fake, Faker};
-use fake::faker::name::en::{Name, LastName, FirstName};
 use fake::faker::internet::en::FreeEmail;
+use fake::faker::name::en::{FirstName, LastName};
+use fake::faker::phone_number::fr_fr::PhoneNumber;
+use fake::Fake;
 
-use serde::Serialize;
-use uuid::Uuid;
+use kafkamion_model::entity::{Driver, Entity};
 
+use super::rng::{uuid, SeededRng};
 use super::EventSource;
 
 pub struct DriverEvent {
-}
-
-#[derive(Debug, Clone, Dummy, Serialize)]
-pub struct Driver {
-    #[dummy(expr = "\"driver\".to_string()")]
-    pub r#type: String,
-    #[dummy(expr = "Uuid::new_v4().to_string()")]
-    pub driver_id: String,
-    #[dummy(faker = "FirstName()")]
-    pub first_name: String,
-    #[dummy(faker = "LastName()")]
-    pub last_name: String,
-    #[dummy(faker = "FreeEmail()")]
-    pub email: String,
-    #[dummy(faker = "Name()")]
-    pub phone: String
+    rng: SeededRng,
 }
 
 impl DriverEvent {
-    pub fn new() -> Self {
+    pub fn new(rng: SeededRng) -> Self {
         Self{
+            rng,
         }
     }
 }
@@ -36,7 +23,19 @@ impl DriverEvent {
 
 impl EventSource<Driver> for DriverEvent {
     fn generate(&self) -> (Vec<String>, Vec<Driver>) {
-        let data: Driver = Faker.fake();
-        (vec![serde_json::to_string(&data).unwrap()], vec![data])
+        let data = self.rng.with(|rng| Driver {
+            driver_id: uuid(rng),
+            first_name: FirstName().fake_with_rng(rng),
+            last_name: LastName().fake_with_rng(rng),
+            email: FreeEmail().fake_with_rng(rng),
+            phone: PhoneNumber().fake_with_rng(rng),
+            version: 1,
+        });
+        let entity = Entity::Driver(data.clone());
+        (vec![serde_json::to_string(&entity).unwrap()], vec![data])
+    }
+
+    fn key(&self, driver: &Driver) -> Option<String> {
+        Some(driver.driver_id.clone())
     }
 }
@@ -0,0 +1,171 @@
+use chrono::{DateTime, Utc};
+use kafkamion_model::entity::{Driver, Truck};
+use kafkamion_model::time_registration::{TimeRegistration, TimeRegistrationType};
+use rand::Rng;
+
+use super::{driver::DriverEvent, rng::SeededRng, route::Route, truck::TruckEvent, EventSource};
+
+/// One out of `POSITION_RATIO` steps of a driving crew ends a period, the others are positions
+const POSITION_RATIO: u32 = 5;
+/// One out of `TURNOVER_RATIO` drivers leaves the fleet at the end of their day
+const TURNOVER_RATIO: u32 = 10;
+
+/// A message of the simulation ready to be published
+pub struct FleetMessage {
+    pub topic: &'static str,
+    pub key: String,
+    pub payload: String,
+}
+
+impl FleetMessage {
+    /// Entity message keyed by its `driver_id` or `truck_id`
+    fn entity(key: &str, payload: String) -> Self {
+        Self {
+            topic: "entity_topic",
+            key: key.to_string(),
+            payload,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CrewState {
+    OffDuty,
+    Driving,
+    OnBreak,
+    DrivingAfterBreak,
+}
+
+/// A driver and the truck they drive
+struct Crew {
+    driver: Driver,
+    truck: Truck,
+    state: CrewState,
+    route: Route,
+    /// Last time the truck was moved along its route
+    moved_at: DateTime<Utc>,
+}
+
+/// A live fleet of crews whose days are simulated one step at a time
+pub struct Fleet {
+    driver_event: DriverEvent,
+    truck_event: TruckEvent,
+    crews: Vec<Crew>,
+    rng: SeededRng,
+}
+
+impl Fleet {
+    pub fn new(rng: SeededRng) -> Self {
+        Self {
+            driver_event: DriverEvent::new(rng.fork()),
+            truck_event: TruckEvent::new(rng.fork()),
+            crews: Vec::new(),
+            rng,
+        }
+    }
+
+    /// Hires `count` crews and returns their entity messages
+    pub fn hire(&mut self, count: i32) -> Vec<FleetMessage> {
+        let mut messages = Vec::new();
+        for _ in 0..count {
+            let (driver_data, mut drivers) = self.driver_event.generate();
+            let (truck_data, mut trucks) = self.truck_event.generate();
+            messages.extend(driver_data.into_iter().map(|payload| FleetMessage::entity(&drivers[0].driver_id, payload)));
+            messages.extend(truck_data.into_iter().map(|payload| FleetMessage::entity(&trucks[0].truck_id, payload)));
+            let truck = trucks.remove(0);
+            self.crews.push(Crew {
+                driver: drivers.remove(0),
+                route: self.rng.with(|rng| Route::from_depot(&truck.truck_id, rng)),
+                truck,
+                state: CrewState::OffDuty,
+                moved_at: Utc::now(),
+            });
+        }
+        messages
+    }
+
+    /// Advances a random crew by one step
+    pub fn step(&mut self) -> Vec<FleetMessage> {
+        if self.crews.is_empty() {
+            return Vec::new();
+        }
+        let (index, registration) = self.rng.with(|rng| {
+            let index = rng.gen_range(0..self.crews.len());
+            let registration = match self.crews[index].state {
+                CrewState::OffDuty => Some(TimeRegistrationType::StartDay),
+                CrewState::Driving if rng.gen_ratio(1, POSITION_RATIO) => Some(TimeRegistrationType::StartBreak),
+                CrewState::OnBreak => Some(TimeRegistrationType::EndBreak),
+                CrewState::DrivingAfterBreak if rng.gen_ratio(1, POSITION_RATIO) => Some(TimeRegistrationType::EndDay),
+                CrewState::Driving | CrewState::DrivingAfterBreak => None,
+            };
+            (index, registration)
+        });
+        let mut messages = self.advance(index, registration);
+
+        let turnover = self.rng.with(|rng| rng.gen_ratio(1, TURNOVER_RATIO));
+        if registration == Some(TimeRegistrationType::EndDay) && turnover {
+            let (data, mut drivers) = self.driver_event.generate();
+            messages.extend(data.into_iter().map(|payload| FleetMessage::entity(&drivers[0].driver_id, payload)));
+            self.crews[index].driver = drivers.remove(0);
+        }
+        messages
+    }
+
+    /// Ends the day of every crew still on duty
+    pub fn finish(&mut self) -> Vec<FleetMessage> {
+        let mut messages = Vec::new();
+        for index in 0..self.crews.len() {
+            let remaining: &[TimeRegistrationType] = match self.crews[index].state {
+                CrewState::OffDuty => &[],
+                CrewState::Driving => &[TimeRegistrationType::StartBreak, TimeRegistrationType::EndBreak, TimeRegistrationType::EndDay],
+                CrewState::OnBreak => &[TimeRegistrationType::EndBreak, TimeRegistrationType::EndDay],
+                CrewState::DrivingAfterBreak => &[TimeRegistrationType::EndDay],
+            };
+            for registration in remaining {
+                messages.extend(self.advance(index, Some(*registration)));
+            }
+        }
+        messages
+    }
+
+    /// Emits the registration, if any, and the position of the crew's truck at the current time,
+    /// the truck having driven since its last position unless the driver was off the road
+    fn advance(&mut self, index: usize, registration: Option<TimeRegistrationType>) -> Vec<FleetMessage> {
+        let crew = &mut self.crews[index];
+        let now = Utc::now();
+        if matches!(crew.state, CrewState::Driving | CrewState::DrivingAfterBreak) {
+            self.rng.with(|rng| crew.route.drive(now - crew.moved_at, rng));
+        }
+        crew.moved_at = now;
+        let timestamp = now.timestamp_micros().to_string();
+        let mut messages = Vec::new();
+
+        if let Some(kind) = registration {
+            let event = TimeRegistration {
+                kind,
+                timestamp: timestamp.clone(),
+                driver_id: crew.driver.driver_id.clone(),
+                truck_id: crew.truck.truck_id.clone(),
+            };
+            messages.push(FleetMessage {
+                topic: "time_registration_topic",
+                key: "time_registration".to_string(),
+                payload: serde_json::to_string(&event).unwrap(),
+            });
+            crew.state = match kind {
+                TimeRegistrationType::StartDay => CrewState::Driving,
+                TimeRegistrationType::StartBreak => CrewState::OnBreak,
+                TimeRegistrationType::EndBreak => CrewState::DrivingAfterBreak,
+                TimeRegistrationType::EndDay => CrewState::OffDuty,
+            };
+        }
+
+        let position = crew.route.position(&crew.truck.truck_id, timestamp);
+        messages.push(FleetMessage {
+            topic: "position_topic",
+            key: "position".to_string(),
+            payload: serde_json::to_string(&position).unwrap(),
+        });
+        messages
+    }
+}
@@ -2,32 +2,44 @@ use std::sync::Arc;
 
 use tracing::info;
 
-use crate::kafka::KafkaClient;
+use crate::sink::EventSink;
+
+use self::fleet::FleetMessage;
 
 pub mod driver;
+pub mod fleet;
 pub mod position;
+pub mod rng;
+pub mod route;
 pub mod time_registration;
 pub mod truck;
+pub mod update;
 
 pub trait EventSource<T> {
     fn generate(&self) -> (Vec<String>, Vec<T>);
+
+    /// Key of the record of an event, the message type when `None`
+    fn key(&self, _event: &T) -> Option<String> {
+        None
+    }
 }
 
 pub async fn generate_and_publish<T: EventSource<U>, U>(
     generator: Arc<T>,
-    client: Arc<KafkaClient>,
+    sink: Arc<dyn EventSink>,
     count: i32,
     topic: &str,
     message_type: &str,
 ) -> Vec<U> {
     let mut data_entity = Vec::new();
     for _ in 0..count {
-        let client = client.clone();
+        let sink = sink.clone();
         let event_generator = generator.clone();
         let (data, ids) = event_generator.generate();
         info!("generated {:?}", data);
-        for message in data {
-            client.publish(topic, &message, message_type).await;
+        for (index, message) in data.iter().enumerate() {
+            let key = ids.get(index).and_then(|event| event_generator.key(event));
+            sink.publish(topic, message, key.as_deref().unwrap_or(message_type)).await;
         }
         for data in ids {
             data_entity.push(data);
@@ -38,21 +50,29 @@ pub async fn generate_and_publish<T: EventSource<U>, U>(
 
 pub async fn generate_and_publish_as_group<T: EventSource<U>, U>(
     generator: Arc<T>,
-    client: Arc<KafkaClient>,
+    sink: Arc<dyn EventSink>,
     count: i32,
     topic: &str,
     message_type: &str,
 ) -> Vec<Vec<U>> {
     let mut data_entity = Vec::new();
     for _ in 0..count {
-        let client = client.clone();
+        let sink = sink.clone();
         let event_generator = generator.clone();
         let (data, ids) = event_generator.generate();
         info!("generated {:?}", data);
         for message in data {
-            client.publish(topic, &message, message_type).await;
+            sink.publish(topic, &message, message_type).await;
         }
         data_entity.push(ids);
     }
     data_entity
 }
+
+pub async fn publish_all(sink: &dyn EventSink, messages: Vec<FleetMessage>) {
+    for message in messages {
+        sink
+            .publish(message.topic, &message.payload, &message.key)
+            .await;
+    }
+}
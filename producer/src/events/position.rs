@@ -1,65 +1,87 @@
-use fake::{
-    faker::address::fr_fr::{Latitude, Longitude},
-    Fake,
+use chrono::{DateTime, Duration, Utc};
+use kafkamion_model::{
+    position::Position,
+    time_registration::{TimeRegistration, TimeRegistrationType},
 };
 use rand::Rng;
-use serde::Serialize;
 
-use super::{time_registration::TimeRegistration, EventSource};
-
-#[derive(Debug, Clone, Serialize)]
-pub struct Position {
-    truck_id: String,
-    latitude: String,
-    longitude: String,
-    timestamp: String,
-}
-
-impl Position {
-    pub fn new(truck_id: String, timestamp: String) -> Self {
-        let fake_latitude = Latitude().fake();
-        let fake_longitude = Longitude().fake();
-
-        Self {
-            truck_id,
-            timestamp,
-            latitude: fake_latitude,
-            longitude: fake_longitude,
-        }
-    }
-}
+use super::{route::Route, rng::SeededRng, EventSource};
 
 pub struct PositionEvent {
     time_registration_pool: Vec<Vec<TimeRegistration>>, //we want complete days of time
                                                         //registration. Otherwise it would be a little difficult for the merger to work
+    /// Time between two positions of a truck on duty
+    interval: Duration,
+    rng: SeededRng,
 }
 
 impl PositionEvent {
-    pub fn new(time_registration_pool: Vec<Vec<TimeRegistration>>) -> Self {
+    pub fn new(time_registration_pool: Vec<Vec<TimeRegistration>>, interval: Duration, rng: SeededRng) -> Self {
         Self {
             time_registration_pool,
+            interval,
+            rng,
         }
     }
 }
 
+fn parse_timestamp(timestamp: &str) -> DateTime<Utc> {
+    timestamp
+        .parse()
+        .ok()
+        .and_then(DateTime::from_timestamp_micros)
+        .expect("time registrations are timestamped in microseconds")
+}
+
 impl EventSource<Position> for PositionEvent {
     fn generate(&self) -> (Vec<String>, Vec<Position>) {
-        let mut rng = rand::thread_rng();
+        self.rng.with(|rng| self.follow(rng))
+    }
+}
+
+impl PositionEvent {
+    /// Follows the truck of random shifts, emitting a position every `interval` while the driver
+    /// is on duty, moving only between the breaks, and one at each time registration.
+    fn follow(&self, rng: &mut impl Rng) -> (Vec<String>, Vec<Position>) {
         let random_time_registrations = self
             .time_registration_pool
             .get(rng.gen_range(0..self.time_registration_pool.len()))
             .unwrap();
 
-        let mut data = Vec::new();
         let mut positions = Vec::new();
+        let mut route: Option<Route> = None;
+        let mut on_duty: Option<(DateTime<Utc>, bool)> = None;
+
+        for time_registration in random_time_registrations {
+            let truck_id = &time_registration.truck_id;
+            let registered_at = parse_timestamp(&time_registration.timestamp);
+            let route = route.get_or_insert_with(|| Route::from_depot(truck_id, rng));
+
+            if let Some((mut time, driving)) = on_duty {
+                while time < registered_at {
+                    let next = (time + self.interval).min(registered_at);
+                    if driving {
+                        route.drive(next - time, rng);
+                    }
+                    time = next;
+                    if time < registered_at {
+                        positions.push(route.position(truck_id, time.timestamp_micros().to_string()));
+                    }
+                }
+            }
+            positions.push(route.position(truck_id, time_registration.timestamp.clone()));
 
-        for random_time_registration in random_time_registrations {
-            let time_registration = random_time_registration.clone();
-            let position = Position::new(time_registration.truck_id, time_registration.timestamp);
-            data.push(serde_json::to_string(&position).unwrap());
-            positions.push(position);
+            on_duty = match time_registration.kind {
+                TimeRegistrationType::StartDay | TimeRegistrationType::EndBreak => Some((registered_at, true)),
+                TimeRegistrationType::StartBreak => Some((registered_at, false)),
+                TimeRegistrationType::EndDay => None,
+            };
         }
 
+        let data = positions
+            .iter()
+            .map(|position| serde_json::to_string(position).unwrap())
+            .collect();
         (data, positions)
     }
 }
@@ -0,0 +1,36 @@
+use std::sync::{Arc, Mutex};
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use uuid::Builder;
+
+/// Random number generator of an event source.
+///
+/// Every generator owns one, forked from the generator of the CLI, so that a run started with
+/// the same `--seed` produces the same events.
+#[derive(Clone)]
+pub struct SeededRng(Arc<Mutex<StdRng>>);
+
+impl SeededRng {
+    /// Seeds the generator, or seeds it from the system entropy without a seed
+    pub fn new(seed: Option<u64>) -> Self {
+        let rng = match seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+        Self(Arc::new(Mutex::new(rng)))
+    }
+
+    /// Creates an independent generator seeded from this one
+    pub fn fork(&self) -> Self {
+        Self::new(Some(self.with(|rng| rng.gen())))
+    }
+
+    pub fn with<T>(&self, f: impl FnOnce(&mut StdRng) -> T) -> T {
+        f(&mut self.0.lock().unwrap())
+    }
+}
+
+/// Random UUID v4 drawn from the given generator
+pub fn uuid(rng: &mut impl Rng) -> String {
+    Builder::from_random_bytes(rng.gen()).into_uuid().to_string()
+}
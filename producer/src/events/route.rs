@@ -0,0 +1,139 @@
+use chrono::Duration;
+use kafkamion_model::position::Position;
+use rand::Rng;
+use tracing::debug;
+
+const EARTH_RADIUS_KM: f64 = 6371.0;
+
+/// Cruising speeds of the trucks on the road
+const MIN_SPEED_KMH: f64 = 60.0;
+const MAX_SPEED_KMH: f64 = 90.0;
+
+#[derive(Debug)]
+pub struct Place {
+    pub name: &'static str,
+    pub latitude: f64,
+    pub longitude: f64,
+}
+
+const fn place(name: &'static str, latitude: f64, longitude: f64) -> Place {
+    Place {
+        name,
+        latitude,
+        longitude,
+    }
+}
+
+/// Depots where the trucks start their first day
+pub const DEPOTS: &[Place] = &[
+    place("Rungis", 48.7590, 2.3520),
+    place("Corbas", 45.6680, 4.9020),
+    place("Lesquin", 50.5890, 3.1120),
+    place("Vitrolles", 43.4550, 5.2480),
+    place("Bruges", 44.8830, -0.6110),
+];
+
+/// Cities where the trucks deliver
+pub const CITIES: &[Place] = &[
+    place("Paris", 48.8566, 2.3522),
+    place("Lyon", 45.7640, 4.8357),
+    place("Marseille", 43.2965, 5.3698),
+    place("Toulouse", 43.6047, 1.4442),
+    place("Nice", 43.7102, 7.2620),
+    place("Nantes", 47.2184, -1.5536),
+    place("Strasbourg", 48.5734, 7.7521),
+    place("Montpellier", 43.6108, 3.8767),
+    place("Bordeaux", 44.8378, -0.5792),
+    place("Lille", 50.6292, 3.0573),
+    place("Rennes", 48.1173, -1.6778),
+    place("Reims", 49.2583, 4.0317),
+    place("Le Havre", 49.4944, 0.1079),
+    place("Dijon", 47.3220, 5.0415),
+    place("Grenoble", 45.1885, 5.7245),
+    place("Clermont-Ferrand", 45.7772, 3.0870),
+    place("Tours", 47.3941, 0.6848),
+    place("Orléans", 47.9030, 1.9093),
+    place("Rouen", 49.4432, 1.0999),
+    place("Metz", 49.1193, 6.1757),
+    place("Limoges", 45.8336, 1.2611),
+];
+
+/// Great-circle distance in kilometers between two coordinates
+pub fn haversine_km(from: (f64, f64), to: (f64, f64)) -> f64 {
+    let (from_latitude, to_latitude) = (from.0.to_radians(), to.0.to_radians());
+    let delta_latitude = to_latitude - from_latitude;
+    let delta_longitude = (to.1 - from.1).to_radians();
+    let a = (delta_latitude / 2.0).sin().powi(2)
+        + from_latitude.cos() * to_latitude.cos() * (delta_longitude / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS_KM * a.sqrt().asin()
+}
+
+/// A truck driving from city to city, starting from its depot
+#[derive(Debug, Clone)]
+pub struct Route {
+    latitude: f64,
+    longitude: f64,
+    destination: &'static Place,
+    speed_kmh: f64,
+}
+
+impl Route {
+    /// Parks the truck at its depot, the same one for a given truck
+    pub fn from_depot(truck_id: &str, rng: &mut impl Rng) -> Self {
+        let index = truck_id.bytes().map(usize::from).sum::<usize>() % DEPOTS.len();
+        let depot = &DEPOTS[index];
+        let mut route = Self {
+            latitude: depot.latitude,
+            longitude: depot.longitude,
+            destination: &CITIES[0],
+            speed_kmh: MIN_SPEED_KMH,
+        };
+        route.next_destination(rng);
+        route
+    }
+
+    /// Heads to another city at a new cruising speed
+    fn next_destination(&mut self, rng: &mut impl Rng) {
+        loop {
+            let destination = &CITIES[rng.gen_range(0..CITIES.len())];
+            if haversine_km(self.coordinates(), (destination.latitude, destination.longitude)) > 1.0 {
+                debug!("heading to {}", destination.name);
+                self.destination = destination;
+                break;
+            }
+        }
+        self.speed_kmh = rng.gen_range(MIN_SPEED_KMH..MAX_SPEED_KMH);
+    }
+
+    fn coordinates(&self) -> (f64, f64) {
+        (self.latitude, self.longitude)
+    }
+
+    /// Drives towards the destination during `duration`, heading to the next city on arrival
+    pub fn drive(&mut self, duration: Duration, rng: &mut impl Rng) {
+        let mut remaining_km = self.speed_kmh * duration.num_milliseconds() as f64 / 3_600_000.0;
+        while remaining_km > 0.0 {
+            let destination = (self.destination.latitude, self.destination.longitude);
+            let distance_km = haversine_km(self.coordinates(), destination);
+            if distance_km <= remaining_km {
+                (self.latitude, self.longitude) = destination;
+                remaining_km -= distance_km;
+                self.next_destination(rng);
+            } else {
+                let fraction = remaining_km / distance_km;
+                self.latitude += (destination.0 - self.latitude) * fraction;
+                self.longitude += (destination.1 - self.longitude) * fraction;
+                remaining_km = 0.0;
+            }
+        }
+    }
+
+    pub fn position(&self, truck_id: &str, timestamp: String) -> Position {
+        Position {
+            truck_id: truck_id.to_string(),
+            latitude: self.latitude,
+            longitude: self.longitude,
+            timestamp,
+        }
+    }
+}
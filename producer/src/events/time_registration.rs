@@ -1,137 +1,226 @@
-use core::fmt;
-use std::fmt::Display;
-
-use chrono::{TimeZone, Utc};
-use fake::{Fake, Faker};
+use chrono::{DateTime, Datelike, Duration, NaiveDate, TimeZone, Utc};
+use clap::Args;
+use kafkamion_model::entity::{Driver, Truck};
+use kafkamion_model::time_registration::{TimeRegistration, TimeRegistrationType};
 use rand::Rng;
-use serde::Serialize;
 
-use super::{driver::Driver, truck::Truck, EventSource};
+use super::rng::SeededRng;
+use super::EventSource;
 
-pub struct TimeRegistrationEvent {
-    truck_pool: Vec<Truck>,
-    driver_pool: Vec<Driver>
+/// Minimum rest of a driver between two shifts of a calendar
+const MINIMUM_REST_HOURS: i64 = 11;
+
+/// Shape of the generated shifts
+#[derive(Debug, Clone, Args)]
+pub struct ShiftConfig {
+    /// Length of a shift in hours, breaks included
+    #[clap(long, default_value_t = 9.0)]
+    pub shift_hours: f64,
+
+    /// Number of breaks per shift
+    #[clap(long, default_value_t = 1)]
+    pub breaks: u32,
+
+    /// Length of a break in minutes
+    #[clap(long, default_value_t = 45)]
+    pub break_minutes: i64,
+
+    /// Probability for a shift to be worked at night, crossing midnight
+    #[clap(long, default_value_t = 0.0)]
+    pub night_shift_ratio: f64,
+
+    /// Number of weeks of shifts generated per driver, 0 for a single shift
+    #[clap(long, default_value_t = 0)]
+    pub weeks: u32,
+
+    /// Days off at the end of each week when generating weeks of shifts
+    #[clap(long, default_value_t = 2)]
+    pub rest_days: u32,
 }
 
-#[derive(Debug, Clone, Serialize)]
-pub struct TimeRegistration {
-    pub r#type: String,
-    pub truck_id: String,
-    pub timestamp: String,
-    pub driver_id: String,
+impl ShiftConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.break_minutes <= 0 {
+            return Err("breaks must last at least a minute".to_string());
+        }
+        if (self.breaks as f64 * self.break_minutes as f64) >= self.shift_hours * 60.0 {
+            return Err("the breaks must be shorter than the shift".to_string());
+        }
+        if !(0.0..=1.0).contains(&self.night_shift_ratio) {
+            return Err("the night shift ratio must be between 0 and 1".to_string());
+        }
+        if self.rest_days > 6 {
+            return Err("a week needs at least one working day".to_string());
+        }
+        Ok(())
+    }
 }
 
-pub enum TimeRegistrationType {
-    StartDay,
-    StartBreak,
-    EndBreak,
-    EndDay,
+/// The time registrations of one driver's day
+#[derive(Debug, Clone)]
+struct Shift {
+    start: DateTime<Utc>,
+    breaks: Vec<(DateTime<Utc>, DateTime<Utc>)>,
+    end: DateTime<Utc>,
 }
 
-impl TimeRegistrationType {
-    pub fn associated_string(&self) -> String {
-        match self {
-            Self::StartDay => "start_day".to_string(),
-            Self::StartBreak => "start_break".to_string(),
-            Self::EndBreak => "end_break".to_string(),
-            Self::EndDay => "end_day".to_string(),
+impl Shift {
+    /// Plans a shift starting on `date`, the day shifts start in the morning and the night
+    /// shifts in the evening so that they end on the next day
+    fn plan(config: &ShiftConfig, date: NaiveDate, night: bool, rng: &mut impl Rng) -> Self {
+        let hour = if night { rng.gen_range(20..23) } else { rng.gen_range(5..10) };
+        let start = Utc
+            .from_utc_datetime(&date.and_hms_opt(hour, rng.gen_range(0..60), 0).unwrap());
+
+        // the shift lasts the configured length, give or take half an hour
+        let length = Duration::minutes((config.shift_hours * 60.0) as i64 + rng.gen_range(-30..=30));
+        let break_length = Duration::minutes(config.break_minutes);
+        let breaks_length = break_length * config.breaks as i32;
+        let driving = (length - breaks_length).max(Duration::minutes(config.breaks as i64 + 1));
+
+        // the breaks split the driving time in equal parts
+        let driving_part = driving / (config.breaks as i32 + 1);
+        let mut time = start;
+        let mut breaks = Vec::new();
+        for _ in 0..config.breaks {
+            time += driving_part;
+            let end_break = time + break_length + Duration::minutes(rng.gen_range(0..15));
+            breaks.push((time, end_break));
+            time = end_break;
         }
+        let end = time + driving_part;
+
+        Self { start, breaks, end }
+    }
+
+    /// Moves the whole shift later in time
+    fn delay(&mut self, by: Duration) {
+        self.start += by;
+        for (start_break, end_break) in &mut self.breaks {
+            *start_break += by;
+            *end_break += by;
+        }
+        self.end += by;
     }
-}
 
-impl Display for TimeRegistrationType {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.associated_string())
+    fn registrations(&self) -> Vec<(TimeRegistrationType, DateTime<Utc>)> {
+        let mut registrations = vec![(TimeRegistrationType::StartDay, self.start)];
+        for (start_break, end_break) in &self.breaks {
+            registrations.push((TimeRegistrationType::StartBreak, *start_break));
+            registrations.push((TimeRegistrationType::EndBreak, *end_break));
+        }
+        registrations.push((TimeRegistrationType::EndDay, self.end));
+        registrations
     }
 }
 
+pub struct TimeRegistrationEvent {
+    truck_pool: Vec<Truck>,
+    driver_pool: Vec<Driver>,
+    config: ShiftConfig,
+    rng: SeededRng,
+}
+
 pub struct TimeRegistrationBuilder {
-    driver: Driver,
-    truck: Truck,
+    driver_id: String,
+    truck_id: String,
     date: String,
     r#type: TimeRegistrationType,
 }
 
 impl TimeRegistrationBuilder {
-    pub fn new(r#type: TimeRegistrationType) -> Self {
-        //default initialization
-        let driver: Driver = Faker.fake();
-        let truck: Truck = Faker.fake();
-        let mut rng = rand::thread_rng();
-        let year = rng.gen_range(2022..2025);
-        let month = rng.gen_range(1..12);
-        let day = rng.gen_range(1..27);
-        let hour = match r#type {
-            TimeRegistrationType::StartDay => rng.gen_range(5..11),
-            TimeRegistrationType::StartBreak => rng.gen_range(12..14),
-            TimeRegistrationType::EndBreak => rng.gen_range(15..18),
-            TimeRegistrationType::EndDay => rng.gen_range(19..23),
-        };
-        let dt = Utc
-            .with_ymd_and_hms(year, month, day, hour, 0, 0)
-            .unwrap()
-            .timestamp_micros()
-            .to_string();
+    /// The driver, the truck and the date are required, so that a registration only holds
+    /// values drawn from the seeded generator
+    pub fn new(r#type: TimeRegistrationType, driver: &Driver, truck: &Truck, date: DateTime<Utc>) -> Self {
         Self {
-            driver,
-            truck,
-            date: dt,
+            driver_id: driver.driver_id.clone(),
+            truck_id: truck.truck_id.clone(),
+            date: date.timestamp_micros().to_string(),
             r#type,
         }
     }
 
-    pub fn with_driver(mut self, driver: Driver) -> Self {
-        self.driver = driver;
-        self
-    }
-
-    pub fn with_truck(mut self, truck: Truck) -> Self {
-        self.truck = truck;
-        self
-    }
-
     pub fn build(&self) -> TimeRegistration {
         TimeRegistration {
-            r#type: self.r#type.to_string(),
-            driver_id: self.driver.clone().driver_id,
-            truck_id: self.truck.clone().truck_id,
+            kind: self.r#type,
+            driver_id: self.driver_id.clone(),
+            truck_id: self.truck_id.clone(),
             timestamp: self.date.clone(),
         }
     }
 }
 
 impl TimeRegistrationEvent {
-    pub fn new(driver_pool: Vec<Driver>, truck_pool: Vec<Truck>) -> Self {
+    pub fn new(driver_pool: Vec<Driver>, truck_pool: Vec<Truck>, config: ShiftConfig, rng: SeededRng) -> Self {
         Self {
             driver_pool,
-            truck_pool
+            truck_pool,
+            config,
+            rng,
         }
     }
+
+    /// Plans a single shift on a random date
+    fn single_shift(&self, rng: &mut impl Rng) -> Vec<Shift> {
+        let date = NaiveDate::from_ymd_opt(rng.gen_range(2022..2025), rng.gen_range(1..=12), rng.gen_range(1..=28)).unwrap();
+        let night = rng.gen_bool(self.config.night_shift_ratio);
+        vec![Shift::plan(&self.config, date, night, rng)]
+    }
+
+    /// Plans `weeks` weeks of shifts starting on a random monday, the last `rest_days` days of
+    /// each week being off. A driver works either days or nights for a whole week, and a shift
+    /// is delayed when needed so that the driver rests at least 11 hours between two shifts.
+    fn calendar(&self, rng: &mut impl Rng) -> Vec<Shift> {
+        let first_day = NaiveDate::from_ymd_opt(rng.gen_range(2022..2025), rng.gen_range(1..=12), rng.gen_range(1..=28)).unwrap();
+        let monday = first_day - Duration::days(first_day.weekday().num_days_from_monday() as i64);
+        let working_days = 7 - self.config.rest_days;
+
+        let mut shifts: Vec<Shift> = Vec::new();
+        for week in 0..self.config.weeks {
+            let night = rng.gen_bool(self.config.night_shift_ratio);
+            for day in 0..working_days {
+                let date = monday + Duration::days((week * 7 + day) as i64);
+                let mut shift = Shift::plan(&self.config, date, night, rng);
+                if let Some(previous) = shifts.last() {
+                    let earliest_start = previous.end + Duration::hours(MINIMUM_REST_HOURS);
+                    if shift.start < earliest_start {
+                        shift.delay(earliest_start - shift.start);
+                    }
+                }
+                shifts.push(shift);
+            }
+        }
+        shifts
+    }
 }
 
 
 impl EventSource<TimeRegistration> for TimeRegistrationEvent {
     fn generate(&self) -> (Vec<String>, Vec<TimeRegistration>) {
-        let time_registration_types = [TimeRegistrationType::StartDay, TimeRegistrationType::StartBreak, TimeRegistrationType::EndBreak, TimeRegistrationType::EndDay];
+        let (random_driver, random_truck, shifts) = self.rng.with(|rng| {
+            //first need to get a random driver
+            let random_driver = self.driver_pool.get(rng.gen_range(0..self.driver_pool.len()));
+            //then the truck
+            let random_truck = self.truck_pool.get(rng.gen_range(0..self.truck_pool.len()));
+
+            let shifts = if self.config.weeks > 0 {
+                self.calendar(rng)
+            } else {
+                self.single_shift(rng)
+            };
+            (random_driver, random_truck, shifts)
+        });
 
-        let mut rng = rand::thread_rng();
-        //first need to get a random driver
-        let random_driver = self.driver_pool.get(rng.gen_range(0..self.driver_pool.len()));
-        //then the truck
-        let random_truck = self.truck_pool.get(rng.gen_range(0..self.truck_pool.len()));
         let mut data = Vec::new();
         let mut events = Vec::new();
-        for time_registration_type in time_registration_types {
-            let event = TimeRegistrationBuilder::new(time_registration_type)
-                .with_driver(random_driver.unwrap().clone())
-                .with_truck(random_truck.unwrap().clone())
-                .build();
-            
-            events.push(event.clone());
-            data.push(serde_json::to_string(&event).unwrap());
+        for shift in shifts {
+            for (time_registration_type, date) in shift.registrations() {
+                let event = TimeRegistrationBuilder::new(time_registration_type, random_driver.unwrap(), random_truck.unwrap(), date).build();
+
+                events.push(event.clone());
+                data.push(serde_json::to_string(&event).unwrap());
+            }
         }
         (data, events)
     }
 }
-
-
@@ -1,32 +1,33 @@
 use fake::faker::automotive::fr_fr::LicencePlate;
-use fake::{Dummy, Fake, Faker};
+use fake::Fake;
 
-use serde::Serialize;
-use uuid::Uuid;
+use kafkamion_model::entity::{Entity, Truck};
 
+use super::rng::{uuid, SeededRng};
 use super::EventSource;
 
-pub struct TruckEvent {}
-
-#[derive(Debug, Clone, Dummy, Serialize)]
-pub struct Truck {
-    #[dummy(expr = "\"truck\".to_string()")]
-    pub r#type: String,
-    #[dummy(expr = "Uuid::new_v4().to_string()")]
-    pub truck_id: String,
-    #[dummy(faker = "LicencePlate()")]
-    pub immatriculation: String,
+pub struct TruckEvent {
+    rng: SeededRng,
 }
 
 impl TruckEvent {
-    pub fn new() -> Self {
-        Self {}
+    pub fn new(rng: SeededRng) -> Self {
+        Self { rng }
     }
 }
 
 impl EventSource<Truck> for TruckEvent {
     fn generate(&self) -> (Vec<String>, Vec<Truck>) {
-        let data: Truck = Faker.fake();
-        (vec![serde_json::to_string(&data).unwrap()], vec![data])
+        let data = self.rng.with(|rng| Truck {
+            truck_id: uuid(rng),
+            immatriculation: LicencePlate().fake_with_rng(rng),
+            version: 1,
+        });
+        let entity = Entity::Truck(data.clone());
+        (vec![serde_json::to_string(&entity).unwrap()], vec![data])
+    }
+
+    fn key(&self, truck: &Truck) -> Option<String> {
+        Some(truck.truck_id.clone())
     }
 }
@@ -0,0 +1,62 @@
+use fake::faker::automotive::fr_fr::LicencePlate;
+use fake::faker::internet::en::FreeEmail;
+use fake::faker::phone_number::fr_fr::PhoneNumber;
+use fake::Fake;
+use kafkamion_model::entity::{Driver, Entity, Truck};
+use rand::Rng;
+use tracing::info;
+
+use crate::sink::EventSink;
+
+use super::rng::SeededRng;
+
+/// Changes to drivers and trucks already published : each of them gets a new version, then is
+/// deleted with a probability of `deletion_ratio`
+pub struct EntityUpdate {
+    deletion_ratio: f64,
+    rng: SeededRng,
+}
+
+impl EntityUpdate {
+    pub fn new(deletion_ratio: f64, rng: SeededRng) -> Self {
+        Self { deletion_ratio, rng }
+    }
+
+    /// Next version of a driver, with a new email and phone number
+    pub fn driver(&self, driver: &Driver) -> Driver {
+        self.rng.with(|rng| Driver {
+            email: FreeEmail().fake_with_rng(rng),
+            phone: PhoneNumber().fake_with_rng(rng),
+            version: driver.version + 1,
+            ..driver.clone()
+        })
+    }
+
+    /// Next version of a truck, registered under a new immatriculation
+    pub fn truck(&self, truck: &Truck) -> Truck {
+        self.rng.with(|rng| Truck {
+            immatriculation: LicencePlate().fake_with_rng(rng),
+            version: truck.version + 1,
+            ..truck.clone()
+        })
+    }
+
+    /// Publishes the next version of each entity, keyed by its id, followed by a tombstone for
+    /// those deleted. Returns the ids of the deleted entities.
+    pub async fn publish(&self, sink: &dyn EventSink, topic: &str, entities: Vec<Entity>) -> Vec<String> {
+        let mut deleted = Vec::new();
+        for entity in entities {
+            let updated = match &entity {
+                Entity::Driver(driver) => Entity::Driver(self.driver(driver)),
+                Entity::Truck(truck) => Entity::Truck(self.truck(truck)),
+            };
+            sink.publish(topic, &serde_json::to_string(&updated).unwrap(), updated.id()).await;
+            if self.rng.with(|rng| rng.gen_bool(self.deletion_ratio)) {
+                sink.delete(topic, updated.id()).await;
+                deleted.push(updated.id().to_string());
+            }
+        }
+        info!("deleted {} entities", deleted.len());
+        deleted
+    }
+}
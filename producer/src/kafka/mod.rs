@@ -1,13 +1,16 @@
 use std::time::Duration;
 
+use futures::future::BoxFuture;
 use rdkafka::{
     consumer::{BaseConsumer, Consumer},
-    producer::{FutureProducer, FutureRecord},
+    producer::{FutureProducer, FutureRecord, Producer},
     util::Timeout,
     ClientConfig,
 };
 use tracing::{error, info};
 
+use crate::sink::EventSink;
+
 pub struct KafkaClient {
     producer: FutureProducer,
 }
@@ -50,6 +53,28 @@ impl KafkaClient {
             .await
             .unwrap();
     }
+
+    pub async fn delete(&self, topic: &str, key: &str) {
+        info!("sent tombstone {} to topic {}", key, topic);
+        self.producer
+            .send(FutureRecord::<str, str>::to(topic).key(key), Timeout::Never)
+            .await
+            .unwrap();
+    }
+}
+
+impl EventSink for KafkaClient {
+    fn publish<'a>(&'a self, topic: &'a str, payload: &'a str, key: &'a str) -> BoxFuture<'a, ()> {
+        Box::pin(KafkaClient::publish(self, topic, payload, key))
+    }
+
+    fn delete<'a>(&'a self, topic: &'a str, key: &'a str) -> BoxFuture<'a, ()> {
+        Box::pin(KafkaClient::delete(self, topic, key))
+    }
+
+    fn flush(&self) {
+        let _ = self.producer.flush(Timeout::After(Duration::from_secs(5)));
+    }
 }
 
 /// Checks if Kafka is available by attempting to connect to the server.
@@ -0,0 +1,4 @@
+pub mod cli;
+pub mod events;
+pub mod kafka;
+pub mod sink;
@@ -1,17 +1,16 @@
+use std::process::ExitCode;
+
 use clap::Parser;
-use cli::{App, Cli};
+use producer::cli::{App, Cli};
 use tracing::info;
 
-mod events;
-mod cli;
-mod kafka;
-
 #[tokio::main]
-async fn main() {
-    tracing_subscriber::fmt().init();
+async fn main() -> ExitCode {
+    tracing_subscriber::fmt().with_writer(std::io::stderr).init();
     info!("tracer init");
     let args = App::parse();
 
-    let result = &Cli::new().execute(args).await;
-
+    match Cli::new().execute(args).await {
+        Ok(code) | Err(code) => code,
+    }
 }
@@ -0,0 +1,73 @@
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    path::PathBuf,
+    sync::Mutex,
+};
+
+use futures::future::{self, BoxFuture};
+use kafkamion_model::entity::Tombstone;
+use tracing::{error, info};
+
+use super::EventSink;
+
+/// Writes the events in one JSONL file per topic, `<directory>/<topic>.jsonl`. The lines of the
+/// events have no key, a tombstone being written as a `Tombstone` line holding its key.
+pub struct FileSink {
+    directory: PathBuf,
+    files: Mutex<HashMap<String, BufWriter<File>>>,
+}
+
+impl FileSink {
+    pub fn new(directory: impl Into<PathBuf>) -> io::Result<Self> {
+        let directory = directory.into();
+        fs::create_dir_all(&directory)?;
+        Ok(Self {
+            directory,
+            files: Mutex::new(HashMap::new()),
+        })
+    }
+
+    fn write(&self, topic: &str, payload: &str) -> io::Result<()> {
+        let mut files = self.files.lock().unwrap();
+        if !files.contains_key(topic) {
+            let path = self.directory.join(format!("{}.jsonl", topic));
+            info!("writing {} to {}", topic, path.display());
+            files.insert(topic.to_string(), BufWriter::new(File::create(path)?));
+        }
+        let file = files.get_mut(topic).unwrap();
+        writeln!(file, "{}", payload)
+    }
+}
+
+impl EventSink for FileSink {
+    fn publish<'a>(&'a self, topic: &'a str, payload: &'a str, _key: &'a str) -> BoxFuture<'a, ()> {
+        if let Err(e) = self.write(topic, payload) {
+            error!("unable to write to {}: {}", topic, e);
+        }
+        Box::pin(future::ready(()))
+    }
+
+    fn delete<'a>(&'a self, topic: &'a str, key: &'a str) -> BoxFuture<'a, ()> {
+        let tombstone = Tombstone {
+            key: key.to_string(),
+            payload: (),
+        };
+        let written = serde_json::to_string(&tombstone)
+            .map_err(io::Error::from)
+            .and_then(|line| self.write(topic, &line));
+        if let Err(e) = written {
+            error!("unable to write the tombstone of {} to {}: {}", key, topic, e);
+        }
+        Box::pin(future::ready(()))
+    }
+
+    fn flush(&self) {
+        for (topic, file) in self.files.lock().unwrap().iter_mut() {
+            if let Err(e) = file.flush() {
+                error!("unable to flush {}: {}", topic, e);
+            }
+        }
+    }
+}
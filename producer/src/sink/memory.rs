@@ -0,0 +1,22 @@
+use futures::future::{self, BoxFuture};
+use kafkamion_broker::{InMemoryBroker, Sink};
+use tracing::error;
+
+use super::EventSink;
+
+/// Publishes the events to the topics of an in-memory broker, for the tests of the pipeline
+impl EventSink for InMemoryBroker {
+    fn publish<'a>(&'a self, topic: &'a str, payload: &'a str, key: &'a str) -> BoxFuture<'a, ()> {
+        if let Err(err) = self.send(topic, key, payload.as_bytes()) {
+            error!("Unable to publish to {}: {}", topic, err);
+        }
+        Box::pin(future::ready(()))
+    }
+
+    fn delete<'a>(&'a self, topic: &'a str, key: &'a str) -> BoxFuture<'a, ()> {
+        if let Err(err) = self.send_tombstone(topic, key) {
+            error!("Unable to delete {} from {}: {}", key, topic, err);
+        }
+        Box::pin(future::ready(()))
+    }
+}
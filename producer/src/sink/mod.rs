@@ -0,0 +1,16 @@
+use futures::future::BoxFuture;
+
+pub mod file;
+pub mod memory;
+pub mod stdout;
+
+/// Destination of the generated events
+pub trait EventSink: Send + Sync {
+    fn publish<'a>(&'a self, topic: &'a str, payload: &'a str, key: &'a str) -> BoxFuture<'a, ()>;
+
+    /// Publishes a tombstone, a record without payload, deleting the entity keyed by `key`
+    fn delete<'a>(&'a self, topic: &'a str, key: &'a str) -> BoxFuture<'a, ()>;
+
+    /// Makes sure every published event is written before the producer exits
+    fn flush(&self) {}
+}
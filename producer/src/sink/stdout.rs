@@ -0,0 +1,32 @@
+use std::io::{self, Write};
+
+use futures::future::{self, BoxFuture};
+
+use super::EventSink;
+
+/// Prints the events as `<topic> <payload>` lines, and the tombstones as
+/// `<topic> tombstone <key>` lines
+#[derive(Default)]
+pub struct StdoutSink {}
+
+impl StdoutSink {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+impl EventSink for StdoutSink {
+    fn publish<'a>(&'a self, topic: &'a str, payload: &'a str, _key: &'a str) -> BoxFuture<'a, ()> {
+        println!("{} {}", topic, payload);
+        Box::pin(future::ready(()))
+    }
+
+    fn delete<'a>(&'a self, topic: &'a str, key: &'a str) -> BoxFuture<'a, ()> {
+        println!("{} tombstone {}", topic, key);
+        Box::pin(future::ready(()))
+    }
+
+    fn flush(&self) {
+        let _ = io::stdout().flush();
+    }
+}
@@ -0,0 +1,50 @@
+use std::sync::Arc;
+
+use kafkamion_broker::InMemoryBroker;
+use producer::events::driver::DriverEvent;
+use producer::events::position::PositionEvent;
+use producer::events::rng::SeededRng;
+use producer::events::time_registration::{ShiftConfig, TimeRegistrationEvent};
+use producer::events::truck::TruckEvent;
+use producer::events::{generate_and_publish, generate_and_publish_as_group};
+
+const TOPICS: [&str; 3] = ["entity_topic", "time_registration_topic", "position_topic"];
+
+/// Publishes what the `position` command generates with `seed`: drivers, trucks, weeks of
+/// shifts and the positions of the trucks
+async fn generate(seed: u64) -> Arc<InMemoryBroker> {
+    let broker = Arc::new(InMemoryBroker::new());
+    let rng = SeededRng::new(Some(seed));
+    let config = ShiftConfig {
+        shift_hours: 9.0,
+        breaks: 2,
+        break_minutes: 30,
+        night_shift_ratio: 0.3,
+        weeks: 2,
+        rest_days: 2,
+    };
+    let drivers = generate_and_publish(Arc::new(DriverEvent::new(rng.fork())), broker.clone(), 3, "entity_topic", "driver").await;
+    let trucks = generate_and_publish(Arc::new(TruckEvent::new(rng.fork())), broker.clone(), 3, "entity_topic", "truck").await;
+    let generator = Arc::new(TimeRegistrationEvent::new(drivers, trucks, config, rng.fork()));
+    let shifts = generate_and_publish_as_group(generator, broker.clone(), 3, "time_registration_topic", "time_registration").await;
+    let generator = Arc::new(PositionEvent::new(shifts, chrono::Duration::minutes(5), rng.fork()));
+    generate_and_publish(generator, broker.clone(), 3, "position_topic", "position").await;
+    broker
+}
+
+#[tokio::test]
+async fn the_same_seed_generates_the_same_events() {
+    let (first, second) = (generate(42).await, generate(42).await);
+    for topic in TOPICS {
+        assert!(!first.records(topic).is_empty(), "nothing published on {}", topic);
+        assert_eq!(first.records(topic), second.records(topic), "{} differs", topic);
+    }
+}
+
+#[tokio::test]
+async fn another_seed_generates_other_events() {
+    let (first, second) = (generate(42).await, generate(43).await);
+    for topic in TOPICS {
+        assert_ne!(first.records(topic), second.records(topic), "{} is the same", topic);
+    }
+}
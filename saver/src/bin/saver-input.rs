@@ -1,8 +1,8 @@
 use chrono::prelude::*;
 use fake::faker::name::en::{FirstName, LastName};
 use fake::faker::phone_number::en::PhoneNumber;
-use fake::{Dummy, Fake, Faker};
-use serde::Serialize;
+use fake::{Fake, Faker};
+use saver::models::messages::Message;
 use std::io::Write;
 use std::process::ExitCode;
 use uuid::Uuid;
@@ -13,55 +13,46 @@ pub struct MessageEvent {
     data: Vec<Message>,
 }
 
-#[derive(Serialize, Dummy, Debug)]
-struct Message {
-    #[dummy(expr = "Uuid::new_v4().to_string()")]
-    driver_id: String,
-    #[dummy(faker = "FirstName()")]
-    first_name: String,
-    #[dummy(faker = "LastName()")]
-    last_name: String,
-    #[dummy(expr = "Faker.fake::<String>() + \"@gmail.com\"")]
-    email: String,
-    #[dummy(faker = "PhoneNumber()")]
-    phone: String,
-    #[dummy(expr = "Uuid::new_v4().to_string()")]
-    truck_id: String,
-    #[dummy(expr = "Faker.fake::<String>()")]
-    immatriculation: String,
-    #[dummy(expr = "Utc::now().to_rfc3339()")]
-    start_time: String,
-    #[dummy(expr = "Utc::now().to_rfc3339()")]
-    end_time: String,
-    #[dummy(expr = "Utc::now().to_rfc3339()")]
-    rest_time: String,
-    #[dummy(expr = "Faker.fake::<f64>() * 180.0 - 90.0")]
-    latitude_start: f64,
-    #[dummy(expr = "Faker.fake::<f64>() * 360.0 - 180.0")]
-    longitude_start: f64,
-    #[dummy(expr = "Utc::now().to_rfc3339()")]
-    timestamp_start: String,
-    #[dummy(expr = "Faker.fake::<f64>() * 180.0 - 90.0")]
-    latitude_end: f64,
-    #[dummy(expr = "Faker.fake::<f64>() * 360.0 - 180.0")]
-    longitude_end: f64,
-    #[dummy(expr = "Utc::now().to_rfc3339()")]
-    timestamp_end: String,
-    #[dummy(expr = "Faker.fake::<f64>() * 180.0 - 90.0")]
-    latitude_rest: f64,
-    #[dummy(expr = "Faker.fake::<f64>() * 360.0 - 180.0")]
-    longitude_rest: f64,
-    #[dummy(expr = "Utc::now().to_rfc3339()")]
-    timestamp_rest: String,
-}
-
 impl MessageEvent {
     fn new() -> Self {
         Self { data: Vec::new() }
     }
 
     fn generate(&mut self) {
-        let message: Message = Faker.fake();
+        let message = Message {
+            driver_id: Uuid::new_v4().to_string(),
+            first_name: FirstName().fake(),
+            last_name: LastName().fake(),
+            email: Faker.fake::<String>() + "@gmail.com",
+            phone: PhoneNumber().fake(),
+            truck_id: Uuid::new_v4().to_string(),
+            immatriculation: Faker.fake::<String>(),
+            start_time: Utc::now().to_rfc3339(),
+            end_time: Utc::now().to_rfc3339(),
+            rest_time: (900..3600).fake::<i64>().to_string(),
+            latitude_start: Faker.fake::<f64>() * 180.0 - 90.0,
+            longitude_start: Faker.fake::<f64>() * 360.0 - 180.0,
+            timestamp_start: Utc::now().to_rfc3339(),
+            latitude_end: Faker.fake::<f64>() * 180.0 - 90.0,
+            longitude_end: Faker.fake::<f64>() * 360.0 - 180.0,
+            timestamp_end: Utc::now().to_rfc3339(),
+            latitude_rest: Faker.fake::<f64>() * 180.0 - 90.0,
+            longitude_rest: Faker.fake::<f64>() * 360.0 - 180.0,
+            timestamp_rest: Utc::now().to_rfc3339(),
+            shift_duration: 0,
+            driving_duration: 0,
+            break_duration: 0,
+            distance_km: 0.0,
+            average_speed_kmh: 0.0,
+            max_speed_kmh: 0.0,
+            offset_start: 0,
+            interpolated_start: false,
+            offset_end: 0,
+            interpolated_end: false,
+            offset_rest: 0,
+            interpolated_rest: false,
+            breaks: Vec::new(),
+        };
         self.data.push(message);
     }
 
@@ -0,0 +1,268 @@
+use crate::models::messages::Message;
+use crate::store::ObjectStore;
+use crate::writer::save_to_parquet;
+use rdkafka::consumer::{CommitMode, Consumer, ConsumerContext, Rebalance, StreamConsumer};
+use rdkafka::{ClientConfig, ClientContext, Message as KafkaMessage, Offset, TopicPartitionList};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Settings of the `consume` mode, read from the environment
+pub struct ConsumerConfig {
+    pub kafka_broker: String,
+    pub group_id: String,
+    pub topic: String,
+    /// Number of buffered reports that triggers a flush
+    pub max_messages: usize,
+    /// Age of the oldest buffered report that triggers a flush
+    pub flush_interval: Duration,
+    /// Attempts at storing a batch before consuming it again
+    pub flush_attempts: u32,
+    /// Time between two attempts at storing a batch
+    pub retry_delay: Duration,
+    /// Local directory where the Parquet files are written before their upload
+    pub spool_dir: PathBuf,
+    /// Prefix of the object keys in the bucket
+    pub key_prefix: String,
+}
+
+impl ConsumerConfig {
+    pub fn from_env() -> Self {
+        let var = |name: &str, default: &str| std::env::var(name).unwrap_or_else(|_| default.to_string());
+        Self {
+            kafka_broker: var("KAFKA_BROKER", "localhost:19092"),
+            group_id: var("KAFKA_GROUP_ID", "saver"),
+            topic: var("REPORT_TOPIC", "report_topic"),
+            max_messages: var("FLUSH_MAX_MESSAGES", "1000").parse().expect("FLUSH_MAX_MESSAGES is not a number"),
+            flush_interval: Duration::from_secs(
+                var("FLUSH_INTERVAL_SECS", "60").parse().expect("FLUSH_INTERVAL_SECS is not a number"),
+            ),
+            flush_attempts: var("FLUSH_ATTEMPTS", "3").parse().expect("FLUSH_ATTEMPTS is not a number"),
+            retry_delay: Duration::from_secs(
+                var("FLUSH_RETRY_DELAY_SECS", "5").parse().expect("FLUSH_RETRY_DELAY_SECS is not a number"),
+            ),
+            spool_dir: PathBuf::from(var("SPOOL_DIR", std::env::temp_dir().to_str().unwrap_or("."))),
+            key_prefix: var("MINIO_KEY_PREFIX", "kafkamion/reports"),
+        }
+    }
+}
+
+/// Reports consumed from one partition since the last flush
+struct PartitionBatch {
+    messages: Vec<Message>,
+    /// Offset of the first consumed record
+    first_offset: i64,
+    /// Offset following the last consumed one
+    next_offset: i64,
+}
+
+impl PartitionBatch {
+    /// Name of the batch file, derived from its partition and the offset it starts at.
+    ///
+    /// A batch consumed again, after a crash between its upload and its commit or by the next
+    /// owner of its partition, starts at the same offset whatever its size, so it overwrites the
+    /// object instead of storing the reports twice.
+    fn name(&self, topic: &str, partition: i32) -> String {
+        format!("{}-{}-{}.parquet", topic, partition, self.first_offset)
+    }
+}
+
+/// Reports consumed since the last flush, by topic and partition
+#[derive(Default)]
+pub struct Batch {
+    partitions: BTreeMap<(String, i32), PartitionBatch>,
+    started_at: Option<Instant>,
+}
+
+impl Batch {
+    /// Adds the report of a record, an invalid or empty one being skipped but still committed
+    pub fn add(&mut self, topic: &str, partition: i32, offset: i64, payload: Option<&[u8]>) {
+        let reports = self.track(topic, partition, offset);
+        match payload.map(serde_json::from_slice::<Message>) {
+            Some(Ok(report)) => reports.messages.push(report),
+            Some(Err(err)) => eprintln!("Skipping invalid report at offset {}: {}", offset, err),
+            None => eprintln!("Skipping empty report at offset {}", offset),
+        }
+    }
+
+    fn track(&mut self, topic: &str, partition: i32, offset: i64) -> &mut PartitionBatch {
+        self.started_at.get_or_insert_with(Instant::now);
+        let batch = self.partitions.entry((topic.to_string(), partition)).or_insert(PartitionBatch {
+            messages: Vec::new(),
+            first_offset: offset,
+            next_offset: offset,
+        });
+        batch.next_offset = offset + 1;
+        batch
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.partitions.is_empty()
+    }
+
+    /// Number of reports in the batch
+    pub fn len(&self) -> usize {
+        self.partitions.values().map(|batch| batch.messages.len()).sum()
+    }
+
+    fn age(&self) -> Duration {
+        self.started_at.map(|started_at| started_at.elapsed()).unwrap_or_default()
+    }
+}
+
+/// What the batches need from the consumer of the reports, Kafka in production
+pub trait ReportConsumer {
+    /// Commits `next_offset` as the offset the partition is consumed from after a restart
+    fn commit(&self, topic: &str, partition: i32, next_offset: i64) -> Result<(), Box<dyn std::error::Error>>;
+
+    /// Consumes the partition again from `offset`
+    fn seek(&self, topic: &str, partition: i32, offset: i64) -> Result<(), Box<dyn std::error::Error>>;
+
+    /// Partitions revoked from the consumer since the last call
+    fn take_revoked(&self) -> Vec<(String, i32)>;
+}
+
+/// Collects the partitions revoked from the consumer, so that their reports are dropped from the
+/// batch instead of being stored and committed after their next owner read them again
+#[derive(Default)]
+pub struct SaverContext {
+    revoked: Mutex<Vec<(String, i32)>>,
+}
+
+impl ClientContext for SaverContext {}
+
+impl ConsumerContext for SaverContext {
+    fn pre_rebalance(&self, rebalance: &Rebalance) {
+        if let Rebalance::Revoke(revoked) = rebalance {
+            let mut partitions = self.revoked.lock().unwrap();
+            partitions.extend(revoked.elements().iter().map(|element| (element.topic().to_string(), element.partition())));
+        }
+    }
+}
+
+type SaverConsumer = StreamConsumer<SaverContext>;
+
+impl ReportConsumer for SaverConsumer {
+    fn commit(&self, topic: &str, partition: i32, next_offset: i64) -> Result<(), Box<dyn std::error::Error>> {
+        let mut offsets = TopicPartitionList::new();
+        offsets.add_partition_offset(topic, partition, Offset::Offset(next_offset))?;
+        Consumer::commit(self, &offsets, CommitMode::Sync)?;
+        Ok(())
+    }
+
+    fn seek(&self, topic: &str, partition: i32, offset: i64) -> Result<(), Box<dyn std::error::Error>> {
+        Consumer::seek(self, topic, partition, Offset::Offset(offset), Duration::from_secs(5))?;
+        Ok(())
+    }
+
+    fn take_revoked(&self) -> Vec<(String, i32)> {
+        std::mem::take(&mut self.context().revoked.lock().unwrap())
+    }
+}
+
+/// Forgets the reports of the partitions revoked since the last call, their next owner consuming
+/// them again from the last committed offset
+pub fn drop_revoked(batch: &mut Batch, consumer: &impl ReportConsumer) {
+    for (topic, partition) in consumer.take_revoked() {
+        if let Some(dropped) = batch.partitions.remove(&(topic.clone(), partition)) {
+            println!("Partition {} of {} revoked, dropping its {} reports", partition, topic, dropped.messages.len());
+        }
+    }
+}
+
+/// Write the reports of a partition to Parquet, upload them and only then commit their offsets
+async fn flush(
+    topic: &str,
+    partition: i32,
+    batch: &PartitionBatch,
+    consumer: &impl ReportConsumer,
+    storage: &impl ObjectStore,
+    config: &ConsumerConfig,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let name = batch.name(topic, partition);
+    let parquet_file = config.spool_dir.join(&name);
+    let parquet_file = parquet_file.to_str().ok_or("Invalid spool directory")?;
+    let key = format!("{}/{}", config.key_prefix, name);
+
+    if !batch.messages.is_empty() {
+        save_to_parquet(&batch.messages, parquet_file)?;
+        storage.upload(parquet_file, &key).await?;
+        std::fs::remove_file(parquet_file)?;
+    }
+    consumer.commit(topic, partition, batch.next_offset)?;
+    println!("Committed {} reports of partition {} of {}", batch.messages.len(), partition, topic);
+    Ok(())
+}
+
+/// Flush the batch of each partition, trying `flush_attempts` times. The consumer is not polled
+/// meanwhile, so the attempts are bounded to stay within `max.poll.interval.ms`: a partition
+/// whose batch could not be stored is rewound to consume it again, so no report is dropped.
+pub async fn flush_batch(
+    batch: &mut Batch,
+    consumer: &impl ReportConsumer,
+    storage: &impl ObjectStore,
+    config: &ConsumerConfig,
+) -> Result<(), Box<dyn std::error::Error>> {
+    drop_revoked(batch, consumer);
+    let partitions = std::mem::take(batch).partitions;
+    for ((topic, partition), reports) in partitions {
+        let mut attempt = 1;
+        while let Err(err) = flush(&topic, partition, &reports, consumer, storage, config).await {
+            if attempt >= config.flush_attempts {
+                eprintln!("Unable to flush {} reports of partition {} of {}, consuming them again: {}", reports.messages.len(), partition, topic, err);
+                consumer.seek(&topic, partition, reports.first_offset)?;
+                break;
+            }
+            eprintln!("Unable to flush {} reports of partition {} of {}, retrying: {}", reports.messages.len(), partition, topic, err);
+            attempt += 1;
+            tokio::time::sleep(config.retry_delay).await;
+        }
+    }
+    Ok(())
+}
+
+/// Consume the reports and store them in the object store until Ctrl-C is received
+pub async fn consume(config: ConsumerConfig, storage: impl ObjectStore) -> Result<(), Box<dyn std::error::Error>> {
+    let consumer: SaverConsumer = ClientConfig::new()
+        .set("group.id", &config.group_id)
+        .set("bootstrap.servers", &config.kafka_broker)
+        .set("enable.auto.commit", "false")
+        // the merger writes the reports in transactions, the aborted ones must not be stored
+        .set("isolation.level", "read_committed")
+        .set("auto.offset.reset", "earliest")
+        .create_with_context(SaverContext::default())?;
+    consumer.subscribe(&[&config.topic])?;
+    println!("Consuming {} as {}", config.topic, config.group_id);
+
+    let mut batch = Batch::default();
+    let mut ticker = tokio::time::interval(Duration::from_secs(1));
+    let interrupted = tokio::signal::ctrl_c();
+    tokio::pin!(interrupted);
+    loop {
+        tokio::select! {
+            received = consumer.recv() => {
+                drop_revoked(&mut batch, &consumer);
+                match received {
+                    Ok(message) => batch.add(message.topic(), message.partition(), message.offset(), message.payload()),
+                    Err(err) => eprintln!("Error while consuming {}: {:?}", config.topic, err),
+                }
+                if batch.len() >= config.max_messages {
+                    flush_batch(&mut batch, &consumer, &storage, &config).await?;
+                }
+            }
+            _ = ticker.tick() => {
+                if !batch.is_empty() && batch.age() >= config.flush_interval {
+                    flush_batch(&mut batch, &consumer, &storage, &config).await?;
+                }
+            }
+            _ = &mut interrupted => {
+                if !batch.is_empty() {
+                    flush_batch(&mut batch, &consumer, &storage, &config).await?;
+                }
+                println!("Saver stopped");
+                return Ok(());
+            }
+        }
+    }
+}
@@ -0,0 +1,90 @@
+//! Erasure of the personal data of a driver from the stored reports, under the GDPR right to
+//! erasure.
+//!
+//! The objects holding reports of the driver are rewritten with its `first_name`, `last_name`,
+//! `email` and `phone` emptied, so erasing a driver again rewrites nothing. The files written by
+//! an older saver, such as the 19 columns of the first `kafkamion/messages.parquet`, are rewritten
+//! with the current schema, their missing columns taking their default value. The `erase` command
+//! exits with an error when an object could not be erased, so that it can be run again.
+
+use crate::models::messages::Message;
+use crate::reader::load_from_parquet;
+use crate::store::ObjectStore;
+use crate::writer::save_to_parquet;
+use kafkamion_model::erasure::{ErasedObject, ErasureAudit, FailedObject, PERSONAL_FIELDS};
+use std::path::Path;
+
+/// Empty the personal fields of the messages of a driver. Returns the number of messages
+/// changed, those already erased being left as they are.
+pub fn erase(messages: &mut [Message], driver_id: &str) -> usize {
+    let mut erased = 0;
+    for message in messages.iter_mut().filter(|message| message.driver_id == driver_id) {
+        let personal = [&mut message.first_name, &mut message.last_name, &mut message.email, &mut message.phone];
+        if personal.iter().any(|field| !field.is_empty()) {
+            personal.into_iter().for_each(String::clear);
+            erased += 1;
+        }
+    }
+    erased
+}
+
+/// Rewrite the Parquet objects under `prefix` holding reports of the driver without their
+/// personal fields, then store the audit of the erasure as JSON under `<prefix>/erasures/`.
+/// The objects are downloaded to and rewritten in `spool_dir`. An object that cannot be erased
+/// is left as it was and recorded in the failures of the audit, the others being erased still.
+pub async fn erase_driver(
+    storage: &impl ObjectStore,
+    prefix: &str,
+    driver_id: &str,
+    spool_dir: &Path,
+) -> Result<ErasureAudit, Box<dyn std::error::Error>> {
+    let mut objects = Vec::new();
+    let mut failures = Vec::new();
+    for key in storage.list(prefix).await? {
+        if !key.ends_with(".parquet") {
+            continue;
+        }
+        let parquet_file = spool_dir.join(key.replace('/', "_"));
+        let parquet_file = parquet_file.to_str().ok_or("Invalid spool directory")?;
+        match erase_object(storage, &key, driver_id, parquet_file).await {
+            Ok(0) => {}
+            Ok(rows) => objects.push(ErasedObject { key, rows }),
+            Err(err) => {
+                eprintln!("Unable to erase driver {} from {}: {}", driver_id, key, err);
+                failures.push(FailedObject { key, reason: err.to_string() });
+            }
+        }
+        if Path::new(parquet_file).exists() {
+            std::fs::remove_file(parquet_file)?;
+        }
+    }
+
+    let erased_at = chrono::Utc::now();
+    let audit = ErasureAudit {
+        driver_id: driver_id.to_string(),
+        erased_at: erased_at.to_rfc3339(),
+        fields: PERSONAL_FIELDS.iter().map(|field| field.to_string()).collect(),
+        objects,
+        failures,
+    };
+    let audit_file = spool_dir.join(format!("erasure-{}.json", driver_id));
+    let audit_file = audit_file.to_str().ok_or("Invalid spool directory")?;
+    std::fs::write(audit_file, serde_json::to_vec_pretty(&audit)?)?;
+    let key = format!("{}/erasures/{}-{}.json", prefix, driver_id, erased_at.format("%Y%m%dT%H%M%S%.f"));
+    storage.upload(audit_file, &key).await?;
+    std::fs::remove_file(audit_file)?;
+    Ok(audit)
+}
+
+/// Erase the reports of the driver from one object, through `parquet_file`. Returns the number
+/// of reports erased, the object being rewritten only when there are some.
+async fn erase_object(storage: &impl ObjectStore, key: &str, driver_id: &str, parquet_file: &str) -> Result<usize, Box<dyn std::error::Error>> {
+    storage.download(key, parquet_file).await?;
+    let mut messages = load_from_parquet(parquet_file)?;
+    let rows = erase(&mut messages, driver_id);
+    if rows > 0 {
+        save_to_parquet(&messages, parquet_file)?;
+        storage.upload(parquet_file, key).await?;
+    }
+    Ok(rows)
+}
@@ -1 +1,7 @@
-pub mod models;
\ No newline at end of file
+pub mod consumer;
+pub mod erasure;
+pub mod minio;
+pub mod models;
+pub mod reader;
+pub mod store;
+pub mod writer;
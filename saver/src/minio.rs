@@ -0,0 +1,83 @@
+use aws_sdk_s3::config::Region;
+use aws_sdk_s3::primitives::ByteStream;
+use aws_sdk_s3::Client;
+
+use crate::store::ObjectStore;
+
+/// Client of the MinIO bucket where the Parquet files are stored
+pub struct MinioClient {
+    client: Client,
+    bucket: String,
+}
+
+impl MinioClient {
+    pub fn new(bucket: String, endpoint: String, access_key: String, secret_key: String) -> Self {
+        let config = aws_sdk_s3::Config::builder()
+            .endpoint_url(endpoint)
+            .credentials_provider(aws_sdk_s3::config::Credentials::new(access_key, secret_key, None, None, "static"))
+            .region(Region::new("us-east-1"))
+            .build();
+        Self {
+            client: Client::from_conf(config),
+            bucket,
+        }
+    }
+
+    /// Build the client from the `MINIO_*` environment variables
+    pub fn from_env() -> Self {
+        let bucket = std::env::var("MINIO_BUCKET").expect("MINIO_BUCKET not set");
+        let endpoint = std::env::var("MINIO_ENDPOINT").expect("MINIO_ENDPOINT not set");
+        let access_key = std::env::var("MINIO_ACCESS_KEY").expect("MINIO_ACCESS_KEY not set");
+        let secret_key = std::env::var("MINIO_SECRET_KEY").expect("MINIO_SECRET_KEY not set");
+        Self::new(bucket, endpoint, access_key, secret_key)
+    }
+}
+
+impl ObjectStore for MinioClient {
+    /// Upload the Parquet file to MinIO
+    async fn upload(&self, file_path: &str, key: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let parquet_data = tokio::fs::read(file_path).await?;
+        let byte_stream = ByteStream::from(parquet_data);
+
+        // Upload on MinIO
+        self.client
+            .put_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .body(byte_stream)
+            .send()
+            .await?;
+
+        println!("File uploaded to MinIO: {}", key);
+        Ok(())
+    }
+
+    /// List the keys of the bucket, one page of up to 1000 keys at a time
+    async fn list(&self, prefix: &str) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        let mut keys = Vec::new();
+        let mut continuation_token = None;
+        loop {
+            let output = self
+                .client
+                .list_objects_v2()
+                .bucket(&self.bucket)
+                .prefix(prefix)
+                .set_continuation_token(continuation_token)
+                .send()
+                .await?;
+            keys.extend(output.contents().iter().filter_map(|object| object.key().map(str::to_string)));
+            match output.next_continuation_token() {
+                Some(token) => continuation_token = Some(token.to_string()),
+                None => return Ok(keys),
+            }
+        }
+    }
+
+    /// Download an object of MinIO to a local file
+    async fn download(&self, key: &str, file_path: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let object = self.client.get_object().bucket(&self.bucket).key(key).send().await?;
+        let data = object.body.collect().await?.into_bytes();
+        tokio::fs::write(file_path, data).await?;
+        Ok(())
+    }
+}
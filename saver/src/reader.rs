@@ -0,0 +1,140 @@
+use crate::models::messages::Message;
+use kafkamion_model::report::Break;
+use parquet::errors::ParquetError;
+use parquet::file::reader::{FileReader, SerializedFileReader};
+use parquet::record::{ListAccessor, Row, RowAccessor};
+use parquet::schema::types::Type;
+use std::collections::HashMap;
+use std::fs::File;
+
+/// Columns of the first schema of the saver, which every file holds. The columns added since
+/// are read as their default value from the files written before them.
+const REQUIRED_COLUMNS: [&str; 19] = [
+    "driver_id",
+    "first_name",
+    "last_name",
+    "email",
+    "phone",
+    "truck_id",
+    "immatriculation",
+    "start_time",
+    "end_time",
+    "rest_time",
+    "latitude_start",
+    "longitude_start",
+    "timestamp_start",
+    "latitude_end",
+    "longitude_end",
+    "timestamp_end",
+    "latitude_rest",
+    "longitude_rest",
+    "timestamp_rest",
+];
+
+/// Index of each field of a group of the schema, by name
+struct Columns(HashMap<String, usize>);
+
+impl Columns {
+    fn of(group: &Type) -> Self {
+        let fields = if group.is_group() { group.get_fields() } else { &[] };
+        Columns(fields.iter().enumerate().map(|(index, field)| (field.name().to_string(), index)).collect())
+    }
+
+    fn index(&self, name: &str) -> Result<usize, ParquetError> {
+        self.0.get(name).copied().ok_or_else(|| ParquetError::General(format!("no {} column", name)))
+    }
+
+    /// Reads a column the file may predate, its default value standing in for it
+    fn optional<T: Default>(&self, name: &str, read: impl FnOnce(usize) -> Result<T, ParquetError>) -> Result<T, ParquetError> {
+        self.0.get(name).map(|index| read(*index)).transpose().map(Option::unwrap_or_default)
+    }
+}
+
+/// Read the messages of a Parquet file written by `save_to_parquet`, or by an older saver
+pub fn load_from_parquet(parquet_file: &str) -> Result<Vec<Message>, Box<dyn std::error::Error>> {
+    let reader = SerializedFileReader::new(File::open(parquet_file)?)?;
+    let schema = reader.metadata().file_metadata().schema();
+    let columns = Columns::of(schema);
+    for name in REQUIRED_COLUMNS {
+        columns.index(name)?;
+    }
+    // the elements of the `breaks` list, under its repeated `list` group
+    let element = columns
+        .0
+        .get("breaks")
+        .map(|index| schema.get_fields()[*index].as_ref())
+        .filter(|breaks| breaks.is_group())
+        .and_then(|breaks| breaks.get_fields().first())
+        .filter(|list| list.is_group())
+        .and_then(|list| list.get_fields().first())
+        .map(|element| Columns::of(element))
+        .unwrap_or(Columns(HashMap::new()));
+
+    let mut messages = Vec::new();
+    for row in reader.get_row_iter(None)? {
+        messages.push(message_from_row(&row, &columns, &element)?);
+    }
+    Ok(messages)
+}
+
+/// Rebuild a message from a row, reading its columns and those of its breaks by name
+fn message_from_row(row: &Row, columns: &Columns, element: &Columns) -> Result<Message, ParquetError> {
+    let mut breaks = Vec::new();
+    if let Some(index) = columns.0.get("breaks") {
+        let list = row.get_list(*index)?;
+        for index in 0..list.len() {
+            let group = list.get_group(index)?;
+            breaks.push(Break {
+                start_time: group.get_string(element.index("start_time")?)?.clone(),
+                end_time: group.get_string(element.index("end_time")?)?.clone(),
+                duration: group.get_long(element.index("duration")?)?,
+                latitude: group.get_double(element.index("latitude")?)?,
+                longitude: group.get_double(element.index("longitude")?)?,
+                offset: element.optional("offset", |index| group.get_long(index))?,
+                interpolated: element.optional("interpolated", |index| group.get_bool(index))?,
+            });
+        }
+    }
+
+    let string = |name: &str| -> Result<String, ParquetError> { Ok(row.get_string(columns.index(name)?)?.clone()) };
+    let double = |name: &str| -> Result<f64, ParquetError> { row.get_double(columns.index(name)?) };
+    let immatriculation = columns.index("immatriculation")?;
+    Ok(Message {
+        driver_id: string("driver_id")?,
+        first_name: string("first_name")?,
+        last_name: string("last_name")?,
+        email: string("email")?,
+        phone: string("phone")?,
+        truck_id: string("truck_id")?,
+        // the immatriculation column has no UTF8 annotation
+        immatriculation: match row.get_bytes(immatriculation) {
+            Ok(bytes) => bytes.as_utf8()?.to_string(),
+            Err(_) => row.get_string(immatriculation)?.clone(),
+        },
+        start_time: string("start_time")?,
+        end_time: string("end_time")?,
+        rest_time: string("rest_time")?,
+        latitude_start: double("latitude_start")?,
+        longitude_start: double("longitude_start")?,
+        timestamp_start: string("timestamp_start")?,
+        latitude_end: double("latitude_end")?,
+        longitude_end: double("longitude_end")?,
+        timestamp_end: string("timestamp_end")?,
+        latitude_rest: double("latitude_rest")?,
+        longitude_rest: double("longitude_rest")?,
+        timestamp_rest: string("timestamp_rest")?,
+        shift_duration: columns.optional("shift_duration", |index| row.get_long(index))?,
+        driving_duration: columns.optional("driving_duration", |index| row.get_long(index))?,
+        break_duration: columns.optional("break_duration", |index| row.get_long(index))?,
+        distance_km: columns.optional("distance_km", |index| row.get_double(index))?,
+        average_speed_kmh: columns.optional("average_speed_kmh", |index| row.get_double(index))?,
+        max_speed_kmh: columns.optional("max_speed_kmh", |index| row.get_double(index))?,
+        offset_start: columns.optional("offset_start", |index| row.get_long(index))?,
+        interpolated_start: columns.optional("interpolated_start", |index| row.get_bool(index))?,
+        offset_end: columns.optional("offset_end", |index| row.get_long(index))?,
+        interpolated_end: columns.optional("interpolated_end", |index| row.get_bool(index))?,
+        offset_rest: columns.optional("offset_rest", |index| row.get_long(index))?,
+        interpolated_rest: columns.optional("interpolated_rest", |index| row.get_bool(index))?,
+        breaks,
+    })
+}
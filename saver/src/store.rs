@@ -0,0 +1,71 @@
+use std::future::Future;
+use std::path::{Path, PathBuf};
+
+/// Destination of the Parquet files
+pub trait ObjectStore {
+    /// Store the file under `key`
+    fn upload(&self, file_path: &str, key: &str) -> impl Future<Output = Result<(), Box<dyn std::error::Error>>>;
+
+    /// Keys of the objects starting with `prefix`
+    fn list(&self, prefix: &str) -> impl Future<Output = Result<Vec<String>, Box<dyn std::error::Error>>>;
+
+    /// Copy the object stored under `key` to the file
+    fn download(&self, key: &str, file_path: &str) -> impl Future<Output = Result<(), Box<dyn std::error::Error>>>;
+}
+
+/// Object store kept in a local directory, the keys being paths relative to it
+pub struct LocalStore {
+    root: PathBuf,
+}
+
+impl LocalStore {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    /// Path of the object stored under `key`
+    pub fn path(&self, key: &str) -> PathBuf {
+        self.root.join(key)
+    }
+
+    /// Keys of the files under `dir`, recursively
+    fn keys(&self, dir: &Path, keys: &mut Vec<String>) -> std::io::Result<()> {
+        for entry in std::fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                self.keys(&path, keys)?;
+            } else if let Ok(relative) = path.strip_prefix(&self.root) {
+                let parts: Vec<String> = relative.iter().map(|part| part.to_string_lossy().into_owned()).collect();
+                keys.push(parts.join("/"));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl ObjectStore for LocalStore {
+    async fn upload(&self, file_path: &str, key: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let path = self.path(key);
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        tokio::fs::copy(file_path, &path).await?;
+        println!("File stored in {}", path.display());
+        Ok(())
+    }
+
+    async fn list(&self, prefix: &str) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        let mut keys = Vec::new();
+        if self.root.is_dir() {
+            self.keys(&self.root, &mut keys)?;
+        }
+        keys.retain(|key| key.starts_with(prefix));
+        keys.sort();
+        Ok(keys)
+    }
+
+    async fn download(&self, key: &str, file_path: &str) -> Result<(), Box<dyn std::error::Error>> {
+        tokio::fs::copy(self.path(key), file_path).await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,219 @@
+use crate::models::messages::Message;
+use parquet::data_type::ByteArray;
+use parquet::file::properties::WriterProperties;
+use parquet::file::writer::{FileWriter, SerializedFileWriter};
+use parquet::schema::parser::parse_message_type;
+use std::fs::File;
+use std::sync::Arc;
+
+/// Write the messages into a Parquet file
+pub fn save_to_parquet(messages: &[Message], parquet_file: &str) -> Result<(), Box<dyn std::error::Error>> {
+    // Define the Parquet schema
+    let message_type = "
+        message schema {
+            REQUIRED BINARY driver_id (UTF8);
+            REQUIRED BINARY first_name (UTF8);
+            REQUIRED BINARY last_name (UTF8);
+            REQUIRED BINARY email (UTF8);
+            REQUIRED BINARY phone (UTF8);
+            REQUIRED BINARY truck_id (UTF8);
+            REQUIRED BINARY immatriculation;
+            REQUIRED BINARY start_time (UTF8);
+            REQUIRED BINARY end_time (UTF8);
+            REQUIRED BINARY rest_time (UTF8);
+            REQUIRED DOUBLE latitude_start;
+            REQUIRED DOUBLE longitude_start;
+            REQUIRED BINARY timestamp_start (UTF8);
+            REQUIRED DOUBLE latitude_end;
+            REQUIRED DOUBLE longitude_end;
+            REQUIRED BINARY timestamp_end (UTF8);
+            REQUIRED DOUBLE latitude_rest;
+            REQUIRED DOUBLE longitude_rest;
+            REQUIRED BINARY timestamp_rest (UTF8);
+            REQUIRED INT64 shift_duration;
+            REQUIRED INT64 driving_duration;
+            REQUIRED INT64 break_duration;
+            REQUIRED DOUBLE distance_km;
+            REQUIRED DOUBLE average_speed_kmh;
+            REQUIRED DOUBLE max_speed_kmh;
+            REQUIRED INT64 offset_start;
+            REQUIRED BOOLEAN interpolated_start;
+            REQUIRED INT64 offset_end;
+            REQUIRED BOOLEAN interpolated_end;
+            REQUIRED INT64 offset_rest;
+            REQUIRED BOOLEAN interpolated_rest;
+            REQUIRED GROUP breaks (LIST) {
+                REPEATED GROUP list {
+                    REQUIRED GROUP element {
+                        REQUIRED BINARY start_time (UTF8);
+                        REQUIRED BINARY end_time (UTF8);
+                        REQUIRED INT64 duration;
+                        REQUIRED DOUBLE latitude;
+                        REQUIRED DOUBLE longitude;
+                        REQUIRED INT64 offset;
+                        REQUIRED BOOLEAN interpolated;
+                    }
+                }
+            }
+        }
+    ";
+    let schema = Arc::new(parse_message_type(message_type)?);
+
+    // Create the Parquet file
+    let file = File::create(parquet_file)?;
+    let props = Arc::new(WriterProperties::builder().build());
+    let mut writer = SerializedFileWriter::new(file, schema, props)?;
+
+    // Add each message to the Parquet file
+    {
+        let mut row_group_writer = writer.next_row_group()?;
+        
+        macro_rules! write_column {
+            ($column_writer:expr, $values:expr, $type:path) => {
+                write_column!($column_writer, $values, $type, None, None)
+            };
+            ($column_writer:expr, $values:expr, $type:path, $def_levels:expr, $rep_levels:expr) => {
+                if let Some(mut col_writer) = $column_writer {
+                    if let $type(ref mut typed_writer) = col_writer {
+                        typed_writer.write_batch(&$values, $def_levels, $rep_levels)?;
+                    }
+                    row_group_writer.close_column(col_writer)?;
+                }
+            };
+        }
+
+        // Write each column
+        let driver_ids: Vec<ByteArray> = messages.iter().map(|m| ByteArray::from(m.driver_id.as_str())).collect();
+        write_column!(row_group_writer.next_column()?, driver_ids, parquet::column::writer::ColumnWriter::ByteArrayColumnWriter);
+
+        let first_names: Vec<ByteArray> = messages.iter().map(|m| ByteArray::from(m.first_name.as_str())).collect();
+        write_column!(row_group_writer.next_column()?, first_names, parquet::column::writer::ColumnWriter::ByteArrayColumnWriter);
+
+        let last_names: Vec<ByteArray> = messages.iter().map(|m| ByteArray::from(m.last_name.as_str())).collect();
+        write_column!(row_group_writer.next_column()?, last_names, parquet::column::writer::ColumnWriter::ByteArrayColumnWriter);
+
+        let emails: Vec<ByteArray> = messages.iter().map(|m| ByteArray::from(m.email.as_str())).collect();
+        write_column!(row_group_writer.next_column()?, emails, parquet::column::writer::ColumnWriter::ByteArrayColumnWriter);
+
+        let phones: Vec<ByteArray> = messages.iter().map(|m| ByteArray::from(m.phone.as_str())).collect();
+        write_column!(row_group_writer.next_column()?, phones, parquet::column::writer::ColumnWriter::ByteArrayColumnWriter);
+
+        let truck_ids: Vec<ByteArray> = messages.iter().map(|m| ByteArray::from(m.truck_id.as_str())).collect();
+        write_column!(row_group_writer.next_column()?, truck_ids, parquet::column::writer::ColumnWriter::ByteArrayColumnWriter);
+
+        let immatriculations: Vec<ByteArray> = messages.iter().map(|m| ByteArray::from(m.immatriculation.as_str())).collect();
+        write_column!(row_group_writer.next_column()?, immatriculations, parquet::column::writer::ColumnWriter::ByteArrayColumnWriter);
+
+        let start_times: Vec<ByteArray> = messages.iter().map(|m| ByteArray::from(m.start_time.as_str())).collect();
+        write_column!(row_group_writer.next_column()?, start_times, parquet::column::writer::ColumnWriter::ByteArrayColumnWriter);
+
+        let end_times: Vec<ByteArray> = messages.iter().map(|m| ByteArray::from(m.end_time.as_str())).collect();
+        write_column!(row_group_writer.next_column()?, end_times, parquet::column::writer::ColumnWriter::ByteArrayColumnWriter);
+
+        let rest_times: Vec<ByteArray> = messages.iter().map(|m| ByteArray::from(m.rest_time.as_str())).collect();
+        write_column!(row_group_writer.next_column()?, rest_times, parquet::column::writer::ColumnWriter::ByteArrayColumnWriter);
+
+        let latitude_starts: Vec<f64> = messages.iter().map(|m| m.latitude_start).collect();
+        write_column!(row_group_writer.next_column()?, latitude_starts, parquet::column::writer::ColumnWriter::DoubleColumnWriter);
+
+        let longitude_starts: Vec<f64> = messages.iter().map(|m| m.longitude_start).collect();
+        write_column!(row_group_writer.next_column()?, longitude_starts, parquet::column::writer::ColumnWriter::DoubleColumnWriter);
+
+        let timestamp_starts: Vec<ByteArray> = messages.iter().map(|m| ByteArray::from(m.timestamp_start.as_str())).collect();
+        write_column!(row_group_writer.next_column()?, timestamp_starts, parquet::column::writer::ColumnWriter::ByteArrayColumnWriter);
+
+        let latitude_ends: Vec<f64> = messages.iter().map(|m| m.latitude_end).collect();
+        write_column!(row_group_writer.next_column()?, latitude_ends, parquet::column::writer::ColumnWriter::DoubleColumnWriter);
+
+        let longitude_ends: Vec<f64> = messages.iter().map(|m| m.longitude_end).collect();
+        write_column!(row_group_writer.next_column()?, longitude_ends, parquet::column::writer::ColumnWriter::DoubleColumnWriter);
+
+        let timestamp_ends: Vec<ByteArray> = messages.iter().map(|m| ByteArray::from(m.timestamp_end.as_str())).collect();
+        write_column!(row_group_writer.next_column()?, timestamp_ends, parquet::column::writer::ColumnWriter::ByteArrayColumnWriter);
+
+        let latitude_rests: Vec<f64> = messages.iter().map(|m| m.latitude_rest).collect();
+        write_column!(row_group_writer.next_column()?, latitude_rests, parquet::column::writer::ColumnWriter::DoubleColumnWriter);
+
+        let longitude_rests: Vec<f64> = messages.iter().map(|m| m.longitude_rest).collect();
+        write_column!(row_group_writer.next_column()?, longitude_rests, parquet::column::writer::ColumnWriter::DoubleColumnWriter);
+
+        let timestamp_rests: Vec<ByteArray> = messages.iter().map(|m| ByteArray::from(m.timestamp_rest.as_str())).collect();
+        write_column!(row_group_writer.next_column()?, timestamp_rests, parquet::column::writer::ColumnWriter::ByteArrayColumnWriter);
+
+        let shift_durations: Vec<i64> = messages.iter().map(|m| m.shift_duration).collect();
+        write_column!(row_group_writer.next_column()?, shift_durations, parquet::column::writer::ColumnWriter::Int64ColumnWriter);
+
+        let driving_durations: Vec<i64> = messages.iter().map(|m| m.driving_duration).collect();
+        write_column!(row_group_writer.next_column()?, driving_durations, parquet::column::writer::ColumnWriter::Int64ColumnWriter);
+
+        let total_break_durations: Vec<i64> = messages.iter().map(|m| m.break_duration).collect();
+        write_column!(row_group_writer.next_column()?, total_break_durations, parquet::column::writer::ColumnWriter::Int64ColumnWriter);
+
+        let distances: Vec<f64> = messages.iter().map(|m| m.distance_km).collect();
+        write_column!(row_group_writer.next_column()?, distances, parquet::column::writer::ColumnWriter::DoubleColumnWriter);
+
+        let average_speeds: Vec<f64> = messages.iter().map(|m| m.average_speed_kmh).collect();
+        write_column!(row_group_writer.next_column()?, average_speeds, parquet::column::writer::ColumnWriter::DoubleColumnWriter);
+
+        let max_speeds: Vec<f64> = messages.iter().map(|m| m.max_speed_kmh).collect();
+        write_column!(row_group_writer.next_column()?, max_speeds, parquet::column::writer::ColumnWriter::DoubleColumnWriter);
+
+        let offset_starts: Vec<i64> = messages.iter().map(|m| m.offset_start).collect();
+        write_column!(row_group_writer.next_column()?, offset_starts, parquet::column::writer::ColumnWriter::Int64ColumnWriter);
+
+        let interpolated_starts: Vec<bool> = messages.iter().map(|m| m.interpolated_start).collect();
+        write_column!(row_group_writer.next_column()?, interpolated_starts, parquet::column::writer::ColumnWriter::BoolColumnWriter);
+
+        let offset_ends: Vec<i64> = messages.iter().map(|m| m.offset_end).collect();
+        write_column!(row_group_writer.next_column()?, offset_ends, parquet::column::writer::ColumnWriter::Int64ColumnWriter);
+
+        let interpolated_ends: Vec<bool> = messages.iter().map(|m| m.interpolated_end).collect();
+        write_column!(row_group_writer.next_column()?, interpolated_ends, parquet::column::writer::ColumnWriter::BoolColumnWriter);
+
+        let offset_rests: Vec<i64> = messages.iter().map(|m| m.offset_rest).collect();
+        write_column!(row_group_writer.next_column()?, offset_rests, parquet::column::writer::ColumnWriter::Int64ColumnWriter);
+
+        let interpolated_rests: Vec<bool> = messages.iter().map(|m| m.interpolated_rest).collect();
+        write_column!(row_group_writer.next_column()?, interpolated_rests, parquet::column::writer::ColumnWriter::BoolColumnWriter);
+
+        // Write the fields of the breaks, one value per break. A message without break has a
+        // single empty entry, the definition level telling the list is empty, and the repetition
+        // level telling whether a value starts a new message.
+        let breaks: Vec<_> = messages.iter().flat_map(|m| m.breaks.iter()).collect();
+        let def_levels: Vec<i16> = messages
+            .iter()
+            .flat_map(|m| if m.breaks.is_empty() { vec![0] } else { vec![1; m.breaks.len()] })
+            .collect();
+        let rep_levels: Vec<i16> = messages
+            .iter()
+            .flat_map(|m| (0..m.breaks.len().max(1)).map(|index| if index == 0 { 0 } else { 1 }))
+            .collect();
+
+        let break_starts: Vec<ByteArray> = breaks.iter().map(|b| ByteArray::from(b.start_time.as_str())).collect();
+        write_column!(row_group_writer.next_column()?, break_starts, parquet::column::writer::ColumnWriter::ByteArrayColumnWriter, Some(def_levels.as_slice()), Some(rep_levels.as_slice()));
+
+        let break_ends: Vec<ByteArray> = breaks.iter().map(|b| ByteArray::from(b.end_time.as_str())).collect();
+        write_column!(row_group_writer.next_column()?, break_ends, parquet::column::writer::ColumnWriter::ByteArrayColumnWriter, Some(def_levels.as_slice()), Some(rep_levels.as_slice()));
+
+        let break_durations: Vec<i64> = breaks.iter().map(|b| b.duration).collect();
+        write_column!(row_group_writer.next_column()?, break_durations, parquet::column::writer::ColumnWriter::Int64ColumnWriter, Some(def_levels.as_slice()), Some(rep_levels.as_slice()));
+
+        let break_latitudes: Vec<f64> = breaks.iter().map(|b| b.latitude).collect();
+        write_column!(row_group_writer.next_column()?, break_latitudes, parquet::column::writer::ColumnWriter::DoubleColumnWriter, Some(def_levels.as_slice()), Some(rep_levels.as_slice()));
+
+        let break_longitudes: Vec<f64> = breaks.iter().map(|b| b.longitude).collect();
+        write_column!(row_group_writer.next_column()?, break_longitudes, parquet::column::writer::ColumnWriter::DoubleColumnWriter, Some(def_levels.as_slice()), Some(rep_levels.as_slice()));
+
+        let break_offsets: Vec<i64> = breaks.iter().map(|b| b.offset).collect();
+        write_column!(row_group_writer.next_column()?, break_offsets, parquet::column::writer::ColumnWriter::Int64ColumnWriter, Some(def_levels.as_slice()), Some(rep_levels.as_slice()));
+
+        let break_interpolations: Vec<bool> = breaks.iter().map(|b| b.interpolated).collect();
+        write_column!(row_group_writer.next_column()?, break_interpolations, parquet::column::writer::ColumnWriter::BoolColumnWriter, Some(def_levels.as_slice()), Some(rep_levels.as_slice()));
+
+        writer.close_row_group(row_group_writer)?;
+    }
+
+    writer.close()?;
+    println!("Parquet file saved to {}", parquet_file);
+    Ok(())
+}
@@ -0,0 +1,216 @@
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use kafkamion_model::report::Report;
+use saver::consumer::{drop_revoked, flush_batch, Batch, ConsumerConfig, ReportConsumer};
+use saver::reader::load_from_parquet;
+use saver::store::{LocalStore, ObjectStore};
+
+/// Consumer recording its commits, each with the objects stored when it happened, and its seeks
+struct RecordingConsumer {
+    objects: PathBuf,
+    commits: Mutex<Vec<(i32, i64, Vec<String>)>>,
+    seeks: Mutex<Vec<(i32, i64)>>,
+    revoked: Mutex<Vec<(String, i32)>>,
+}
+
+impl RecordingConsumer {
+    fn new(objects: &LocalStore) -> Self {
+        Self {
+            objects: objects.path(""),
+            commits: Mutex::new(Vec::new()),
+            seeks: Mutex::new(Vec::new()),
+            revoked: Mutex::new(Vec::new()),
+        }
+    }
+}
+
+impl ReportConsumer for RecordingConsumer {
+    fn commit(&self, _topic: &str, partition: i32, next_offset: i64) -> Result<(), Box<dyn std::error::Error>> {
+        let stored = match std::fs::read_dir(self.objects.join("reports")) {
+            Ok(entries) => entries.map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned()).collect(),
+            Err(_) => Vec::new(),
+        };
+        self.commits.lock().unwrap().push((partition, next_offset, stored));
+        Ok(())
+    }
+
+    fn seek(&self, _topic: &str, partition: i32, offset: i64) -> Result<(), Box<dyn std::error::Error>> {
+        self.seeks.lock().unwrap().push((partition, offset));
+        Ok(())
+    }
+
+    fn take_revoked(&self) -> Vec<(String, i32)> {
+        std::mem::take(&mut self.revoked.lock().unwrap())
+    }
+}
+
+/// Store failing the given number of uploads before storing the files in a local directory
+struct FailingStore {
+    failures: Mutex<u32>,
+    store: LocalStore,
+}
+
+impl ObjectStore for FailingStore {
+    async fn upload(&self, file_path: &str, key: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let failed = {
+            let mut failures = self.failures.lock().unwrap();
+            let failed = *failures > 0;
+            *failures = failures.saturating_sub(1);
+            failed
+        };
+        if failed {
+            return Err("object store unavailable".into());
+        }
+        self.store.upload(file_path, key).await
+    }
+
+    async fn list(&self, prefix: &str) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        self.store.list(prefix).await
+    }
+
+    async fn download(&self, key: &str, file_path: &str) -> Result<(), Box<dyn std::error::Error>> {
+        self.store.download(key, file_path).await
+    }
+}
+
+fn config(spool: &tempfile::TempDir) -> ConsumerConfig {
+    ConsumerConfig {
+        kafka_broker: String::new(),
+        group_id: "saver".to_string(),
+        topic: "report_topic".to_string(),
+        max_messages: 1000,
+        flush_interval: Duration::from_secs(60),
+        flush_attempts: 2,
+        retry_delay: Duration::ZERO,
+        spool_dir: spool.path().to_path_buf(),
+        key_prefix: "reports".to_string(),
+    }
+}
+
+fn report(driver_id: &str) -> Vec<u8> {
+    let report = Report {
+        driver_id: driver_id.to_string(),
+        first_name: "Jane".to_string(),
+        last_name: "Doe".to_string(),
+        email: "jane.doe@example.com".to_string(),
+        phone: "+33600000000".to_string(),
+        truck_id: "T-1".to_string(),
+        immatriculation: "AB-123-CD".to_string(),
+        start_time: "1736143200000000".to_string(),
+        end_time: "1736172000000000".to_string(),
+        rest_time: "0".to_string(),
+        latitude_start: 48.8566,
+        longitude_start: 2.3522,
+        timestamp_start: "1736143200000000".to_string(),
+        latitude_end: 48.9,
+        longitude_end: 2.4,
+        timestamp_end: "1736172000000000".to_string(),
+        latitude_rest: 0.0,
+        longitude_rest: 0.0,
+        timestamp_rest: String::new(),
+        shift_duration: 28800,
+        driving_duration: 28800,
+        break_duration: 0,
+        distance_km: 0.0,
+        average_speed_kmh: 0.0,
+        max_speed_kmh: 0.0,
+        offset_start: 0,
+        interpolated_start: false,
+        offset_end: 0,
+        interpolated_end: false,
+        offset_rest: 0,
+        interpolated_rest: false,
+        breaks: Vec::new(),
+    };
+    serde_json::to_vec(&report).unwrap()
+}
+
+/// A batch of the reports of the drivers, consumed from `first_offset` of partition 0
+fn batch(first_offset: i64, drivers: &[&str]) -> Batch {
+    let mut batch = Batch::default();
+    for (offset, driver_id) in (first_offset..).zip(drivers) {
+        batch.add("report_topic", 0, offset, Some(&report(driver_id)));
+    }
+    batch
+}
+
+#[test]
+fn a_batch_tracks_its_reports_by_partition() {
+    let mut batch = batch(10, &["D-1", "D-2"]);
+    batch.add("report_topic", 1, 4, Some(&report("D-3")));
+    // invalid and empty records are skipped, their offsets still committed
+    batch.add("report_topic", 1, 5, Some(b"not a report"));
+    batch.add("report_topic", 1, 6, None);
+    assert_eq!(batch.len(), 3);
+    assert!(!batch.is_empty());
+}
+
+#[tokio::test]
+async fn the_offsets_are_committed_once_the_batch_is_uploaded() {
+    let spool = tempfile::tempdir().unwrap();
+    let objects = tempfile::tempdir().unwrap();
+    let store = FailingStore { failures: Mutex::new(1), store: LocalStore::new(objects.path()) };
+    let consumer = RecordingConsumer::new(&store.store);
+
+    let mut batch = batch(10, &["D-1", "D-2"]);
+    flush_batch(&mut batch, &consumer, &store, &config(&spool)).await.unwrap();
+
+    // the second attempt stored the batch, named after its partition and first offset
+    let name = "report_topic-0-10.parquet".to_string();
+    assert_eq!(*consumer.commits.lock().unwrap(), [(0, 12, vec![name])]);
+    assert!(consumer.seeks.lock().unwrap().is_empty());
+    assert!(batch.is_empty());
+}
+
+#[tokio::test]
+async fn a_batch_that_cannot_be_stored_is_consumed_again() {
+    let spool = tempfile::tempdir().unwrap();
+    let objects = tempfile::tempdir().unwrap();
+    let store = FailingStore { failures: Mutex::new(2), store: LocalStore::new(objects.path()) };
+    let consumer = RecordingConsumer::new(&store.store);
+
+    let mut batch = batch(10, &["D-1", "D-2"]);
+    flush_batch(&mut batch, &consumer, &store, &config(&spool)).await.unwrap();
+
+    assert!(consumer.commits.lock().unwrap().is_empty());
+    assert_eq!(*consumer.seeks.lock().unwrap(), [(0, 10)]);
+    assert!(store.list("reports/").await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn a_batch_consumed_again_overwrites_its_object() {
+    let spool = tempfile::tempdir().unwrap();
+    let objects = tempfile::tempdir().unwrap();
+    let store = LocalStore::new(objects.path());
+    let consumer = RecordingConsumer::new(&store);
+
+    // stored but not committed before a crash, then consumed again with the next report
+    flush_batch(&mut batch(10, &["D-1", "D-2"]), &consumer, &store, &config(&spool)).await.unwrap();
+    flush_batch(&mut batch(10, &["D-1", "D-2", "D-3"]), &consumer, &store, &config(&spool)).await.unwrap();
+
+    assert_eq!(store.list("reports/").await.unwrap(), ["reports/report_topic-0-10.parquet"]);
+    let path = store.path("reports/report_topic-0-10.parquet");
+    let drivers: Vec<String> = load_from_parquet(path.to_str().unwrap()).unwrap().into_iter().map(|report| report.driver_id).collect();
+    assert_eq!(drivers, ["D-1", "D-2", "D-3"]);
+}
+
+#[tokio::test]
+async fn the_reports_of_a_revoked_partition_are_dropped() {
+    let spool = tempfile::tempdir().unwrap();
+    let objects = tempfile::tempdir().unwrap();
+    let store = LocalStore::new(objects.path());
+    let consumer = RecordingConsumer::new(&store);
+
+    let mut batch = batch(10, &["D-1", "D-2"]);
+    batch.add("report_topic", 1, 4, Some(&report("D-3")));
+    consumer.revoked.lock().unwrap().push(("report_topic".to_string(), 0));
+    drop_revoked(&mut batch, &consumer);
+    assert_eq!(batch.len(), 1);
+
+    flush_batch(&mut batch, &consumer, &store, &config(&spool)).await.unwrap();
+    assert_eq!(store.list("reports/").await.unwrap(), ["reports/report_topic-1-4.parquet"]);
+    let committed: Vec<(i32, i64)> = consumer.commits.lock().unwrap().iter().map(|(partition, offset, _)| (*partition, *offset)).collect();
+    assert_eq!(committed, [(1, 5)]);
+}